@@ -0,0 +1,199 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use mal::eval;
+use mal::read;
+use mal::repl;
+use mal::{MalDataType, MalResult};
+use rustyline::Result;
+
+const USAGE: &str = "\
+usage: mal repl [--history PATH]
+       mal run FILE [ARGS...]
+       mal eval -e EXPR
+       mal check FILE";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Repl { history: Option<String> },
+    Run { path: String, argv: Vec<String> },
+    Eval { expr: String },
+    Check { path: String },
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<Command> {
+    let command = match args.next()?.as_str() {
+        "repl" => match (args.next().as_deref(), args.next()) {
+            (None, _) => Command::Repl { history: None },
+            (Some("--history"), Some(history)) => Command::Repl {
+                history: Some(history),
+            },
+            _ => return None,
+        },
+        "run" => Command::Run {
+            path: args.next()?,
+            argv: args.by_ref().collect(),
+        },
+        "eval" => match (args.next().as_deref(), args.next()) {
+            (Some("-e"), Some(expr)) => Command::Eval { expr },
+            _ => return None,
+        },
+        "check" => Command::Check { path: args.next()? },
+        _ => return None,
+    };
+    // Anything left over is a mistake, except script arguments for `run`
+    args.next().is_none().then_some(command)
+}
+
+/// Evaluates every form in `path`, like `load-file`, with `argv` bound to
+/// `*ARGV*`. Errors are reported on stderr.
+fn run(path: &str, argv: Vec<String>) -> bool {
+    let Some(forms) = read_file(path) else {
+        return false;
+    };
+    let mal_env = repl::new_env(argv);
+    for form in forms {
+        if let Err(e) = eval::eval(&form, &mal_env) {
+            eprintln!("Error: {}", e);
+            return false;
+        }
+    }
+    true
+}
+
+/// Evaluates every form in `expr` and returns the last value.
+fn eval_expr(expr: &str) -> MalResult<MalDataType> {
+    let mal_env = repl::new_env(vec![]);
+    let mut value = MalDataType::Nil;
+    for form in read::read_all(expr)? {
+        value = eval::eval(&form, &mal_env)?;
+    }
+    Ok(value)
+}
+
+/// Reads every form in `path`, reporting a reader error with the file
+/// name and a caret under the offending source.
+fn read_file(path: &str) -> Option<Vec<MalDataType>> {
+    let src = match fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return None;
+        }
+    };
+    match read::read_all(&src) {
+        Ok(forms) => Some(forms),
+        Err(e) => {
+            eprintln!("{}: {}", path, e.annotate(&src));
+            None
+        }
+    }
+}
+
+/// Reads `path` without evaluating it, reporting the first reader error.
+fn check(path: &str) -> bool {
+    read_file(path).is_some()
+}
+
+fn start_repl(history: Option<String>) -> Result<()> {
+    if history.is_some() && !cfg!(feature = "with-file-history") {
+        eprintln!("mal: --history needs a build with the with-file-history feature");
+        process::exit(2);
+    }
+    let mal_env = repl::new_env(vec![]);
+    repl::banner(&mal_env);
+    repl::run(
+        &mut repl::editor()?,
+        history.as_deref().unwrap_or(repl::HISTORY),
+        |line| repl::rep(line, &mal_env),
+    )
+}
+
+fn main() -> Result<()> {
+    let Some(command) = parse_args(env::args().skip(1)) else {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    };
+
+    match command {
+        Command::Repl { history } => return start_repl(history),
+        Command::Run { path, argv } => {
+            if !run(&path, argv) {
+                process::exit(1);
+            }
+        }
+        Command::Eval { expr } => match eval_expr(&expr) {
+            Ok(value) => println!("{}", value),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                process::exit(1);
+            }
+        },
+        Command::Check { path } => {
+            if !check(&path) {
+                process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Option<Command> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse(&["repl"]), Some(Command::Repl { history: None }));
+        assert_eq!(
+            parse(&["repl", "--history", "h.txt"]),
+            Some(Command::Repl {
+                history: Some("h.txt".to_owned())
+            })
+        );
+        assert_eq!(
+            parse(&["run", "a.mal", "x", "--history"]),
+            Some(Command::Run {
+                path: "a.mal".to_owned(),
+                argv: vec!["x".to_owned(), "--history".to_owned()],
+            })
+        );
+        assert_eq!(
+            parse(&["eval", "-e", "(+ 1 2)"]),
+            Some(Command::Eval {
+                expr: "(+ 1 2)".to_owned()
+            })
+        );
+        assert_eq!(
+            parse(&["check", "a.mal"]),
+            Some(Command::Check {
+                path: "a.mal".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for args in [
+            &[][..],
+            &["frobnicate"],
+            &["repl", "--history"],
+            &["repl", "--verbose", "h.txt"],
+            &["repl", "extra"],
+            &["repl", "--history", "h.txt", "extra"],
+            &["run"],
+            &["eval", "(+ 1 2)"],
+            &["eval", "-e"],
+            &["eval", "-e", "1", "2"],
+            &["check"],
+            &["check", "a.mal", "b.mal"],
+        ] {
+            assert_eq!(parse(args), None, "{:?}", args);
+        }
+    }
+}
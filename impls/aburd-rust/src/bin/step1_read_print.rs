@@ -1,45 +1,8 @@
-use mal::environment::MalEnvironment;
-use mal::read;
-use rustyline::error::ReadlineError;
-use rustyline::{DefaultEditor, Result};
+use mal::repl;
+use rustyline::Result;
 
 fn main() -> Result<()> {
-    // `()` can be used when no completer is required
-    let mut rl = DefaultEditor::new()?;
-    let mut mal_env = MalEnvironment::new();
-    #[cfg(feature = "with-file-history")]
-    if rl.load_history("history.txt").is_err() {
-        println!("No previous history.");
-    }
-
-    loop {
-        let readline = rl.readline("user> ");
-        match readline {
-            Ok(line) => {
-                rl.add_history_entry(line.as_str())?;
-                match read::read_str(&line, &mal_env) {
-                    Ok(m_type) => {
-                        println!("{}", m_type.to_string());
-                    }
-                    Err(e) => eprintln!("unbalanced"),
-                }
-            }
-            Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
-                break;
-            }
-            Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
-                break;
-            }
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
-            }
-        }
-    }
-
-    #[cfg(feature = "with-file-history")]
-    rl.save_history("history.txt");
-    Ok(())
+    repl::run(&mut repl::editor()?, repl::HISTORY, |line| {
+        repl::each_form(line, |form| println!("{}", form))
+    })
 }
@@ -0,0 +1,10 @@
+use mal::environment::MalEnvironment;
+use mal::repl;
+use rustyline::Result;
+
+fn main() -> Result<()> {
+    let mal_env = MalEnvironment::new();
+    repl::run(&mut repl::editor()?, repl::HISTORY, |line| {
+        repl::rep(line, &mal_env)
+    })
+}
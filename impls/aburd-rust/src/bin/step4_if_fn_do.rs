@@ -0,0 +1,9 @@
+use mal::repl;
+use rustyline::Result;
+
+fn main() -> Result<()> {
+    let mal_env = repl::new_env(vec![]);
+    repl::run(&mut repl::editor()?, repl::HISTORY, |line| {
+        repl::rep(line, &mal_env)
+    })
+}
@@ -0,0 +1,23 @@
+use std::env;
+use std::process;
+
+use mal::repl;
+use rustyline::Result;
+
+fn main() -> Result<()> {
+    // `step9_try FILE ARGS...` runs FILE with ARGS bound to *ARGV*
+    let mut args = env::args().skip(1);
+    let script = args.next();
+    let mal_env = repl::new_env(args.collect());
+    if let Some(path) = script {
+        if let Err(e) = repl::load_file(path, &mal_env) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    repl::run(&mut repl::editor()?, repl::HISTORY, |line| {
+        repl::rep(line, &mal_env)
+    })
+}
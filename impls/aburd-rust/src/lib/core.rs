@@ -0,0 +1,930 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rustyline::DefaultEditor;
+
+use crate::{
+    environment::{MalEnvironment, WeakEnvironment},
+    eval, print, read, MalBuiltin, MalClosure, MalDataType, MalError, MalMapKey, MalResult,
+};
+
+type CoreFn = fn(&[MalDataType]) -> MalResult<MalDataType>;
+
+/// The builtins every root `MalEnvironment` starts with.
+pub fn ns() -> Vec<MalBuiltin> {
+    let builtins: [(&'static str, CoreFn); 61] = [
+        ("+", add),
+        ("-", sub),
+        ("*", mul),
+        ("/", div),
+        ("=", eq),
+        ("<", lt),
+        ("<=", le),
+        (">", gt),
+        (">=", ge),
+        ("list", list),
+        ("list?", is_list),
+        ("empty?", is_empty),
+        ("count", count),
+        ("pr-str", pr_str),
+        ("str", str),
+        ("prn", prn),
+        ("println", println),
+        ("read-string", read_string),
+        ("slurp", slurp),
+        ("atom", atom),
+        ("atom?", is_atom),
+        ("deref", deref),
+        ("reset!", reset),
+        ("swap!", swap),
+        ("cons", cons),
+        ("concat", concat),
+        ("vec", vec),
+        ("nth", nth),
+        ("first", first),
+        ("rest", rest),
+        ("throw", throw),
+        ("apply", apply),
+        ("map", map),
+        ("nil?", is_nil),
+        ("true?", is_true),
+        ("false?", is_false),
+        ("symbol", symbol),
+        ("symbol?", is_symbol),
+        ("keyword", keyword),
+        ("keyword?", is_keyword),
+        ("vector", vector),
+        ("vector?", is_vector),
+        ("sequential?", is_sequential),
+        ("hash-map", hash_map),
+        ("map?", is_map),
+        ("assoc", assoc),
+        ("dissoc", dissoc),
+        ("get", get),
+        ("contains?", contains),
+        ("keys", keys),
+        ("vals", vals),
+        ("meta", meta),
+        ("with-meta", with_meta),
+        ("readline", readline),
+        ("time-ms", time_ms),
+        ("conj", conj),
+        ("seq", seq),
+        ("string?", is_string),
+        ("number?", is_number),
+        ("fn?", is_fn),
+        ("macro?", is_macro),
+    ];
+    builtins
+        .into_iter()
+        .map(|(name, func)| MalBuiltin {
+            name,
+            func: Rc::new(func),
+            meta: None,
+        })
+        .collect()
+}
+
+/// The builtins that evaluate in the root environment `root`. They only
+/// hold it weakly, since `root` holds them.
+pub fn root_ns(root: &MalEnvironment) -> Vec<MalBuiltin> {
+    let eval_root = root.downgrade();
+    let load_root = root.downgrade();
+    vec![
+        MalBuiltin {
+            name: "eval",
+            func: Rc::new(move |args| eval(args, &upgrade("eval", &eval_root)?)),
+            meta: None,
+        },
+        MalBuiltin {
+            name: "load-file",
+            func: Rc::new(move |args| load_file(args, &upgrade("load-file", &load_root)?)),
+            meta: None,
+        },
+    ]
+}
+
+fn upgrade(name: &str, root: &WeakEnvironment) -> MalResult<MalEnvironment> {
+    root.upgrade().ok_or_else(|| {
+        MalError::InvalidArgs(format!("{}: the root environment no longer exists", name))
+    })
+}
+
+/// Checks that exactly `N` arguments were passed and hands them back as an
+/// array, so callers can destructure them.
+fn expect_args<'a, const N: usize>(
+    name: &str,
+    args: &'a [MalDataType],
+) -> MalResult<&'a [MalDataType; N]> {
+    args.try_into().map_err(|_| {
+        MalError::InvalidArgs(format!(
+            "{}: expected {} arguments, got {}",
+            name,
+            N,
+            args.len()
+        ))
+    })
+}
+
+/// Folds `args` pairwise with `int_op`, switching to `float_op` as soon as
+/// either side is a float. `int_op` returns `None` on overflow.
+fn fold_numbers(
+    name: &str,
+    init: MalDataType,
+    args: &[MalDataType],
+    int_op: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> MalResult<MalDataType> {
+    args.iter().try_fold(init, |acc, arg| match (&acc, arg) {
+        (MalDataType::Int(a), MalDataType::Int(b)) => int_op(*a, *b)
+            .map(MalDataType::Int)
+            .ok_or_else(|| MalError::InvalidArgs(format!("{}: integer overflow", name))),
+        (MalDataType::Int(a), MalDataType::Float(b)) => {
+            Ok(MalDataType::Float(float_op(*a as f64, *b)))
+        }
+        (MalDataType::Float(a), MalDataType::Int(b)) => {
+            Ok(MalDataType::Float(float_op(*a, *b as f64)))
+        }
+        (MalDataType::Float(a), MalDataType::Float(b)) => Ok(MalDataType::Float(float_op(*a, *b))),
+        (MalDataType::Int(_) | MalDataType::Float(_), bad) | (bad, _) => Err(
+            MalError::InvalidArgs(format!("{}: expected a number, got {}", name, bad)),
+        ),
+    })
+}
+
+/// `(- x)` and `(/ x)` apply to the identity, the way Clojure does.
+fn split_first<'a>(
+    name: &str,
+    identity: MalDataType,
+    args: &'a [MalDataType],
+) -> MalResult<(MalDataType, &'a [MalDataType])> {
+    match args {
+        [] => Err(MalError::InvalidArgs(format!(
+            "{}: expected at least 1 argument",
+            name
+        ))),
+        [_] => Ok((identity, args)),
+        [first, rest @ ..] => Ok((first.clone(), rest)),
+    }
+}
+
+fn add(args: &[MalDataType]) -> MalResult<MalDataType> {
+    fold_numbers("+", MalDataType::Int(0), args, i64::checked_add, |a, b| {
+        a + b
+    })
+}
+
+fn sub(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let (first, rest) = split_first("-", MalDataType::Int(0), args)?;
+    fold_numbers("-", first, rest, i64::checked_sub, |a, b| a - b)
+}
+
+fn mul(args: &[MalDataType]) -> MalResult<MalDataType> {
+    fold_numbers("*", MalDataType::Int(1), args, i64::checked_mul, |a, b| {
+        a * b
+    })
+}
+
+/// Only integer division by zero is an error; floats give `##Inf` or `##NaN`.
+fn div(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let (first, rest) = split_first("/", MalDataType::Int(1), args)?;
+    rest.iter().try_fold(first, |acc, arg| match (&acc, arg) {
+        (MalDataType::Int(_), MalDataType::Int(0)) => {
+            Err(MalError::InvalidArgs("/: division by zero".to_owned()))
+        }
+        _ => fold_numbers(
+            "/",
+            acc,
+            std::slice::from_ref(arg),
+            i64::checked_div,
+            |a, b| a / b,
+        ),
+    })
+}
+
+fn num_cmp(name: &str, a: &MalDataType, b: &MalDataType) -> MalResult<Option<Ordering>> {
+    match (a, b) {
+        (MalDataType::Int(a), MalDataType::Int(b)) => Ok(Some(a.cmp(b))),
+        (MalDataType::Int(a), MalDataType::Float(b)) => Ok((*a as f64).partial_cmp(b)),
+        (MalDataType::Float(a), MalDataType::Int(b)) => Ok(a.partial_cmp(&(*b as f64))),
+        (MalDataType::Float(a), MalDataType::Float(b)) => Ok(a.partial_cmp(b)),
+        (MalDataType::Int(_) | MalDataType::Float(_), bad) | (bad, _) => Err(
+            MalError::InvalidArgs(format!("{}: expected a number, got {}", name, bad)),
+        ),
+    }
+}
+
+/// Chained comparison, so `(< 1 2 3)` checks every adjacent pair.
+fn compare(name: &str, args: &[MalDataType], pred: fn(Ordering) -> bool) -> MalResult<MalDataType> {
+    if args.is_empty() {
+        return Err(MalError::InvalidArgs(format!(
+            "{}: expected at least 1 argument",
+            name
+        )));
+    }
+    for pair in args.windows(2) {
+        if !num_cmp(name, &pair[0], &pair[1])?.is_some_and(pred) {
+            return Ok(MalDataType::Boolean(false));
+        }
+    }
+    Ok(MalDataType::Boolean(true))
+}
+
+fn lt(args: &[MalDataType]) -> MalResult<MalDataType> {
+    compare("<", args, Ordering::is_lt)
+}
+
+fn le(args: &[MalDataType]) -> MalResult<MalDataType> {
+    compare("<=", args, Ordering::is_le)
+}
+
+fn gt(args: &[MalDataType]) -> MalResult<MalDataType> {
+    compare(">", args, Ordering::is_gt)
+}
+
+fn ge(args: &[MalDataType]) -> MalResult<MalDataType> {
+    compare(">=", args, Ordering::is_ge)
+}
+
+fn eq(args: &[MalDataType]) -> MalResult<MalDataType> {
+    if args.is_empty() {
+        return Err(MalError::InvalidArgs(
+            "=: expected at least 1 argument".to_owned(),
+        ));
+    }
+    Ok(MalDataType::Boolean(
+        args.windows(2).all(|pair| pair[0] == pair[1]),
+    ))
+}
+
+fn list(args: &[MalDataType]) -> MalResult<MalDataType> {
+    Ok(MalDataType::List(args.to_vec(), None))
+}
+
+fn is_list(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("list?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::List(_, _))))
+}
+
+fn is_empty(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("empty?", args)?;
+    let empty = match x {
+        MalDataType::Nil => true,
+        MalDataType::List(items, _) | MalDataType::Vector(items, _) => items.is_empty(),
+        MalDataType::HashMap(map, _) => map.is_empty(),
+        MalDataType::String(s) => s.is_empty(),
+        x => {
+            return Err(MalError::InvalidArgs(format!(
+                "empty?: expected a collection, got {}",
+                x
+            )))
+        }
+    };
+    Ok(MalDataType::Boolean(empty))
+}
+
+fn count(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("count", args)?;
+    let count = match x {
+        MalDataType::Nil => 0,
+        MalDataType::List(items, _) | MalDataType::Vector(items, _) => items.len(),
+        MalDataType::HashMap(map, _) => map.len(),
+        MalDataType::String(s) => s.chars().count(),
+        x => {
+            return Err(MalError::InvalidArgs(format!(
+                "count: expected a collection, got {}",
+                x
+            )))
+        }
+    };
+    Ok(MalDataType::Int(count as i64))
+}
+
+fn join(args: &[MalDataType], print_readably: bool, sep: &str) -> String {
+    args.iter()
+        .map(|arg| print::pr_str(arg, print_readably))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn pr_str(args: &[MalDataType]) -> MalResult<MalDataType> {
+    Ok(MalDataType::String(join(args, true, " ")))
+}
+
+fn str(args: &[MalDataType]) -> MalResult<MalDataType> {
+    Ok(MalDataType::String(join(args, false, "")))
+}
+
+fn prn(args: &[MalDataType]) -> MalResult<MalDataType> {
+    println!("{}", join(args, true, " "));
+    Ok(MalDataType::Nil)
+}
+
+fn println(args: &[MalDataType]) -> MalResult<MalDataType> {
+    println!("{}", join(args, false, " "));
+    Ok(MalDataType::Nil)
+}
+
+fn read_string(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [MalDataType::String(s)] = args else {
+        return Err(MalError::InvalidArgs(
+            "read-string: expected a string".to_owned(),
+        ));
+    };
+    match read::read_str(s) {
+        Ok(form) => Ok(form),
+        Err(e) if e.kind == read::MalReaderErrorKind::NoForm => Ok(MalDataType::Nil),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn slurp(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [MalDataType::String(path)] = args else {
+        return Err(MalError::InvalidArgs(
+            "slurp: expected a file path".to_owned(),
+        ));
+    };
+    fs::read_to_string(path)
+        .map(MalDataType::String)
+        .map_err(|e| MalError::Io(format!("slurp: {}: {}", path, e)))
+}
+
+/// `(eval form)` evaluates `form` in the root scope, whatever scope the
+/// call itself appears in.
+fn eval(args: &[MalDataType], root: &MalEnvironment) -> MalResult<MalDataType> {
+    let [form] = expect_args("eval", args)?;
+    eval::eval(form, root)
+}
+
+/// `(load-file path)` evaluates every form in the file in the root scope.
+fn load_file(args: &[MalDataType], root: &MalEnvironment) -> MalResult<MalDataType> {
+    let [MalDataType::String(path)] = args else {
+        return Err(MalError::InvalidArgs(
+            "load-file: expected a file path".to_owned(),
+        ));
+    };
+    let src = fs::read_to_string(path)
+        .map_err(|e| MalError::Io(format!("load-file: {}: {}", path, e)))?;
+
+    for form in read::read_all(&src)? {
+        eval::eval(&form, root)?;
+    }
+    Ok(MalDataType::Nil)
+}
+
+fn atom(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [value] = expect_args("atom", args)?;
+    Ok(MalDataType::Atom(Rc::new(RefCell::new(value.clone()))))
+}
+
+fn is_atom(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("atom?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::Atom(_))))
+}
+
+fn expect_atom<'a>(name: &str, x: &'a MalDataType) -> MalResult<&'a Rc<RefCell<MalDataType>>> {
+    match x {
+        MalDataType::Atom(atom) => Ok(atom),
+        x => Err(MalError::InvalidArgs(format!(
+            "{}: expected an atom, got {}",
+            name, x
+        ))),
+    }
+}
+
+fn deref(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("deref", args)?;
+    Ok(expect_atom("deref", x)?.borrow().clone())
+}
+
+fn reset(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x, value] = expect_args("reset!", args)?;
+    *expect_atom("reset!", x)?.borrow_mut() = value.clone();
+    Ok(value.clone())
+}
+
+/// `(swap! a f x y)` sets `a` to `(f @a x y)` and returns the new value.
+fn swap(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x, f, rest @ ..] = args else {
+        return Err(MalError::InvalidArgs(
+            "swap!: expected an atom and a function".to_owned(),
+        ));
+    };
+    let atom = expect_atom("swap!", x)?;
+
+    let mut f_args = vec![atom.borrow().clone()];
+    f_args.extend_from_slice(rest);
+    // The borrow is released before calling, so `f` may deref the atom too
+    let value = eval::apply(f, &f_args)?;
+    *atom.borrow_mut() = value.clone();
+    Ok(value)
+}
+
+fn expect_seq<'a>(name: &str, x: &'a MalDataType) -> MalResult<&'a [MalDataType]> {
+    match x {
+        MalDataType::List(items, _) | MalDataType::Vector(items, _) => Ok(items),
+        MalDataType::Nil => Ok(&[]),
+        x => Err(MalError::InvalidArgs(format!(
+            "{}: expected a list or vector, got {}",
+            name, x
+        ))),
+    }
+}
+
+fn cons(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x, seq] = expect_args("cons", args)?;
+    let mut items = vec![x.clone()];
+    items.extend_from_slice(expect_seq("cons", seq)?);
+    Ok(MalDataType::List(items, None))
+}
+
+fn concat(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let mut items = vec![];
+    for seq in args {
+        items.extend_from_slice(expect_seq("concat", seq)?);
+    }
+    Ok(MalDataType::List(items, None))
+}
+
+fn vec(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [seq] = expect_args("vec", args)?;
+    Ok(MalDataType::Vector(expect_seq("vec", seq)?.to_vec(), None))
+}
+
+fn nth(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [seq, MalDataType::Int(i)] = expect_args("nth", args)? else {
+        return Err(MalError::InvalidArgs(
+            "nth: expected an integer index".to_owned(),
+        ));
+    };
+    let items = expect_seq("nth", seq)?;
+    usize::try_from(*i)
+        .ok()
+        .and_then(|i| items.get(i))
+        .cloned()
+        .ok_or_else(|| {
+            MalError::InvalidArgs(format!(
+                "nth: index {} out of range for {} items",
+                i,
+                items.len()
+            ))
+        })
+}
+
+fn first(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [seq] = expect_args("first", args)?;
+    Ok(expect_seq("first", seq)?
+        .first()
+        .cloned()
+        .unwrap_or(MalDataType::Nil))
+}
+
+fn rest(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [seq] = expect_args("rest", args)?;
+    let items = expect_seq("rest", seq)?;
+    Ok(MalDataType::List(
+        items.iter().skip(1).cloned().collect(),
+        None,
+    ))
+}
+
+fn throw(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [value] = expect_args("throw", args)?;
+    Err(MalError::Thrown(value.clone()))
+}
+
+/// `(apply f a b [c d])` calls `(f a b c d)`.
+fn apply(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [f, init @ .., last] = args else {
+        return Err(MalError::InvalidArgs(
+            "apply: expected a function and an argument list".to_owned(),
+        ));
+    };
+    let mut f_args = init.to_vec();
+    f_args.extend_from_slice(expect_seq("apply", last)?);
+    eval::apply(f, &f_args)
+}
+
+fn map(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [f, seq] = expect_args("map", args)?;
+    expect_seq("map", seq)?
+        .iter()
+        .map(|x| eval::apply(f, std::slice::from_ref(x)))
+        .collect::<MalResult<_>>()
+        .map(|items| MalDataType::List(items, None))
+}
+
+fn is_nil(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("nil?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::Nil)))
+}
+
+fn is_true(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("true?", args)?;
+    Ok(MalDataType::Boolean(matches!(
+        x,
+        MalDataType::Boolean(true)
+    )))
+}
+
+fn is_false(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("false?", args)?;
+    Ok(MalDataType::Boolean(matches!(
+        x,
+        MalDataType::Boolean(false)
+    )))
+}
+
+fn symbol(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [MalDataType::String(name)] = args else {
+        return Err(MalError::InvalidArgs(
+            "symbol: expected a string".to_owned(),
+        ));
+    };
+    Ok(MalDataType::Symbol(name.to_owned()))
+}
+
+fn is_symbol(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("symbol?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::Symbol(_))))
+}
+
+fn keyword(args: &[MalDataType]) -> MalResult<MalDataType> {
+    match args {
+        [MalDataType::String(name)] => Ok(MalDataType::Keyword(format!(":{}", name))),
+        [MalDataType::Keyword(name)] => Ok(MalDataType::Keyword(name.to_owned())),
+        _ => Err(MalError::InvalidArgs(
+            "keyword: expected a string".to_owned(),
+        )),
+    }
+}
+
+fn is_keyword(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("keyword?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::Keyword(_))))
+}
+
+fn vector(args: &[MalDataType]) -> MalResult<MalDataType> {
+    Ok(MalDataType::Vector(args.to_vec(), None))
+}
+
+fn is_vector(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("vector?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::Vector(_, _))))
+}
+
+fn is_sequential(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("sequential?", args)?;
+    Ok(MalDataType::Boolean(matches!(
+        x,
+        MalDataType::List(_, _) | MalDataType::Vector(_, _)
+    )))
+}
+
+fn is_map(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("map?", args)?;
+    Ok(MalDataType::Boolean(matches!(
+        x,
+        MalDataType::HashMap(_, _)
+    )))
+}
+
+/// Returns the map to look keys up in, treating `nil` as an empty map.
+fn expect_map<'a>(
+    name: &str,
+    x: &'a MalDataType,
+) -> MalResult<Cow<'a, HashMap<MalMapKey, MalDataType>>> {
+    match x {
+        MalDataType::HashMap(map, _) => Ok(Cow::Borrowed(map)),
+        MalDataType::Nil => Ok(Cow::Owned(HashMap::new())),
+        x => Err(MalError::InvalidArgs(format!(
+            "{}: expected a hash-map, got {}",
+            name, x
+        ))),
+    }
+}
+
+fn expect_key(name: &str, x: &MalDataType) -> MalResult<MalMapKey> {
+    MalMapKey::from_data(x).ok_or_else(|| {
+        MalError::InvalidArgs(format!(
+            "{}: expected a string or keyword key, got {}",
+            name, x
+        ))
+    })
+}
+
+/// Inserts alternating keys and values from `kvs` into `map`.
+fn insert_pairs(
+    name: &str,
+    mut map: HashMap<MalMapKey, MalDataType>,
+    kvs: &[MalDataType],
+) -> MalResult<MalDataType> {
+    if !kvs.len().is_multiple_of(2) {
+        return Err(MalError::InvalidArgs(format!(
+            "{}: expected an even number of keys and values",
+            name
+        )));
+    }
+    for pair in kvs.chunks(2) {
+        map.insert(expect_key(name, &pair[0])?, pair[1].clone());
+    }
+    Ok(MalDataType::HashMap(map, None))
+}
+
+fn hash_map(args: &[MalDataType]) -> MalResult<MalDataType> {
+    insert_pairs("hash-map", HashMap::new(), args)
+}
+
+fn assoc(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map, kvs @ ..] = args else {
+        return Err(MalError::InvalidArgs(
+            "assoc: expected a hash-map".to_owned(),
+        ));
+    };
+    insert_pairs("assoc", expect_map("assoc", map)?.into_owned(), kvs)
+}
+
+fn dissoc(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map, keys @ ..] = args else {
+        return Err(MalError::InvalidArgs(
+            "dissoc: expected a hash-map".to_owned(),
+        ));
+    };
+    let mut map = expect_map("dissoc", map)?.into_owned();
+    for key in keys {
+        map.remove(&expect_key("dissoc", key)?);
+    }
+    Ok(MalDataType::HashMap(map, None))
+}
+
+fn get(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map, key] = expect_args("get", args)?;
+    Ok(expect_map("get", map)?
+        .get(&expect_key("get", key)?)
+        .cloned()
+        .unwrap_or(MalDataType::Nil))
+}
+
+fn contains(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map, key] = expect_args("contains?", args)?;
+    Ok(MalDataType::Boolean(
+        expect_map("contains?", map)?.contains_key(&expect_key("contains?", key)?),
+    ))
+}
+
+fn keys(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map] = expect_args("keys", args)?;
+    Ok(MalDataType::List(
+        expect_map("keys", map)?
+            .keys()
+            .map(MalMapKey::to_data)
+            .collect(),
+        None,
+    ))
+}
+
+fn vals(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [map] = expect_args("vals", args)?;
+    Ok(MalDataType::List(
+        expect_map("vals", map)?.values().cloned().collect(),
+        None,
+    ))
+}
+
+/// Metadata is kept on lists, vectors, maps and functions; other values
+/// always have `nil`.
+fn meta(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("meta", args)?;
+    let meta = match x {
+        MalDataType::List(_, meta)
+        | MalDataType::Vector(_, meta)
+        | MalDataType::HashMap(_, meta) => meta,
+        MalDataType::Builtin(builtin) => &builtin.meta,
+        MalDataType::Closure(closure) => &closure.meta,
+        _ => return Ok(MalDataType::Nil),
+    };
+    Ok(meta.as_deref().cloned().unwrap_or(MalDataType::Nil))
+}
+
+/// Returns a copy of the collection or function `x` carrying `meta`; `x`
+/// is unchanged.
+fn with_meta(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x, meta] = expect_args("with-meta", args)?;
+    let meta = Some(Rc::new(meta.clone()));
+    match x {
+        MalDataType::List(items, _) => Ok(MalDataType::List(items.clone(), meta)),
+        MalDataType::Vector(items, _) => Ok(MalDataType::Vector(items.clone(), meta)),
+        MalDataType::HashMap(map, _) => Ok(MalDataType::HashMap(map.clone(), meta)),
+        MalDataType::Builtin(builtin) => Ok(MalDataType::Builtin(MalBuiltin {
+            meta,
+            ..builtin.clone()
+        })),
+        MalDataType::Closure(closure) => Ok(MalDataType::Closure(Rc::new(MalClosure {
+            meta,
+            ..(**closure).clone()
+        }))),
+        x => Err(MalError::InvalidArgs(format!(
+            "with-meta: expected a collection or function, got {}",
+            x
+        ))),
+    }
+}
+
+thread_local! {
+    // Kept across calls so `readline` has history, like the REPL itself
+    static EDITOR: RefCell<Option<DefaultEditor>> = const { RefCell::new(None) };
+}
+
+/// `(readline prompt)` reads a line from the user, or returns `nil` at EOF.
+fn readline(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [MalDataType::String(prompt)] = args else {
+        return Err(MalError::InvalidArgs(
+            "readline: expected a prompt string".to_owned(),
+        ));
+    };
+    EDITOR.with_borrow_mut(|editor| {
+        if editor.is_none() {
+            *editor =
+                Some(DefaultEditor::new().map_err(|e| MalError::Io(format!("readline: {}", e)))?);
+        }
+        let editor = editor.as_mut().expect("editor was just created");
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if !line.is_empty() {
+                    let _ = editor.add_history_entry(&line);
+                }
+                Ok(MalDataType::String(line))
+            }
+            Err(_) => Ok(MalDataType::Nil),
+        }
+    })
+}
+
+fn time_ms(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [] = expect_args("time-ms", args)?;
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| MalError::Io(format!("time-ms: {}", e)))?;
+    Ok(MalDataType::Int(elapsed.as_millis() as i64))
+}
+
+/// `(conj coll x y)` adds to the front of a list, in turn, and to the end
+/// of a vector.
+fn conj(args: &[MalDataType]) -> MalResult<MalDataType> {
+    match args {
+        [MalDataType::List(items, _), xs @ ..] => Ok(MalDataType::List(
+            xs.iter().rev().chain(items).cloned().collect(),
+            None,
+        )),
+        [MalDataType::Vector(items, _), xs @ ..] => Ok(MalDataType::Vector(
+            items.iter().chain(xs).cloned().collect(),
+            None,
+        )),
+        _ => Err(MalError::InvalidArgs(
+            "conj: expected a list or vector".to_owned(),
+        )),
+    }
+}
+
+/// `(seq x)` turns a collection or string into a list, or `nil` if empty.
+fn seq(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("seq", args)?;
+    let items: Vec<_> = match x {
+        MalDataType::List(items, _) | MalDataType::Vector(items, _) => items.clone(),
+        MalDataType::String(s) => s
+            .chars()
+            .map(|c| MalDataType::String(c.to_string()))
+            .collect(),
+        MalDataType::Nil => vec![],
+        x => {
+            return Err(MalError::InvalidArgs(format!(
+                "seq: expected a collection or string, got {}",
+                x
+            )))
+        }
+    };
+    if items.is_empty() {
+        Ok(MalDataType::Nil)
+    } else {
+        Ok(MalDataType::List(items, None))
+    }
+}
+
+fn is_string(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("string?", args)?;
+    Ok(MalDataType::Boolean(matches!(x, MalDataType::String(_))))
+}
+
+fn is_number(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("number?", args)?;
+    Ok(MalDataType::Boolean(matches!(
+        x,
+        MalDataType::Int(_) | MalDataType::Float(_)
+    )))
+}
+
+fn is_fn(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("fn?", args)?;
+    Ok(MalDataType::Boolean(match x {
+        MalDataType::Builtin(_) => true,
+        MalDataType::Closure(closure) => !closure.is_macro,
+        _ => false,
+    }))
+}
+
+fn is_macro(args: &[MalDataType]) -> MalResult<MalDataType> {
+    let [x] = expect_args("macro?", args)?;
+    Ok(MalDataType::Boolean(
+        matches!(x, MalDataType::Closure(closure) if closure.is_macro),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_do_arithmetic() -> MalResult<()> {
+        use MalDataType::{Float, Int};
+
+        assert_eq!(add(&[Int(1), Int(2), Int(3)])?, Int(6));
+        assert_eq!(add(&[])?, Int(0));
+        assert_eq!(sub(&[Int(5)])?, Int(-5));
+        assert_eq!(sub(&[Int(5), Float(0.5)])?, Float(4.5));
+        assert_eq!(mul(&[Int(2), Int(3), Int(4)])?, Int(24));
+        assert_eq!(div(&[Int(7), Int(2)])?, Int(3));
+        assert_eq!(div(&[Float(1.0), Int(4)])?, Float(0.25));
+        assert_eq!(div(&[Float(1.0), Int(0)])?, Float(f64::INFINITY));
+        assert_eq!(div(&[Int(1), Float(0.0)])?, Float(f64::INFINITY));
+        Ok(())
+    }
+
+    #[test]
+    fn can_compare() -> MalResult<()> {
+        use MalDataType::{Boolean, Float, Int, List, Vector};
+
+        assert_eq!(lt(&[Int(1), Int(2), Int(3)])?, Boolean(true));
+        assert_eq!(lt(&[Int(1), Int(3), Int(2)])?, Boolean(false));
+        assert_eq!(ge(&[Float(2.5), Int(2)])?, Boolean(true));
+        assert_eq!(
+            eq(&[
+                List(vec![Int(1), Int(2)], None),
+                Vector(vec![Int(1), Int(2)], None)
+            ])?,
+            Boolean(true)
+        );
+        assert_eq!(eq(&[Int(1), Float(1.0)])?, Boolean(false));
+        Ok(())
+    }
+
+    #[test]
+    fn collections_carry_metadata() -> MalResult<()> {
+        use MalDataType::{Boolean, Int, Nil, String, Vector};
+
+        let v = Vector(vec![Int(1), Int(2)], None);
+        let m = String("a".to_owned());
+        let with = with_meta(&[v.clone(), m.clone()])?;
+        assert_eq!(meta(std::slice::from_ref(&with))?, m);
+        assert_eq!(meta(std::slice::from_ref(&v))?, Nil);
+        assert_eq!(eq(&[v, with])?, Boolean(true));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_arithmetic() {
+        use MalDataType::{Int, Nil};
+
+        assert!(add(&[Int(i64::MAX), Int(1)]).is_err());
+        assert!(div(&[Int(1), Int(0)]).is_err());
+        assert!(div(&[Int(1), Int(2), Int(0)]).is_err());
+        assert!(sub(&[]).is_err());
+        assert!(mul(&[Int(1), Nil]).is_err());
+    }
+
+    #[test]
+    fn can_conj_and_seq() -> MalResult<()> {
+        use MalDataType::{Int, List, Nil, String, Vector};
+
+        assert_eq!(
+            conj(&[List(vec![Int(1)], None), Int(2), Int(3)])?,
+            List(vec![Int(3), Int(2), Int(1)], None)
+        );
+        assert_eq!(
+            conj(&[Vector(vec![Int(1)], None), Int(2), Int(3)])?,
+            Vector(vec![Int(1), Int(2), Int(3)], None)
+        );
+        assert_eq!(
+            seq(&[String("ab".to_owned())])?,
+            List(vec![String("a".to_owned()), String("b".to_owned())], None)
+        );
+        assert_eq!(seq(&[Vector(vec![], None)])?, Nil);
+        Ok(())
+    }
+}
@@ -1,8 +1,113 @@
-#[derive(Debug)]
-pub struct MalEnvironment {}
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Debug,
+    rc::{Rc, Weak},
+};
+
+use crate::{core, MalDataType, MalError, MalResult};
+
+#[derive(Default)]
+struct Scope {
+    data: HashMap<String, MalDataType>,
+    outer: Option<MalEnvironment>,
+}
+
+/// A lexical scope. Cloning is cheap and yields a handle to the same scope,
+/// so closures and child scopes can share it.
+#[derive(Clone, Default)]
+pub struct MalEnvironment(Rc<RefCell<Scope>>);
 
 impl MalEnvironment {
+    /// Creates a root scope seeded with the `core` builtins.
     pub fn new() -> Self {
-        Self {}
+        let env = Self::default();
+        for builtin in core::ns().into_iter().chain(core::root_ns(&env)) {
+            env.define(builtin.name, MalDataType::Builtin(builtin));
+        }
+        env
+    }
+
+    /// Creates an empty scope nested inside this one, e.g. for `let*` or a
+    /// function call.
+    pub fn child(&self) -> Self {
+        Self(Rc::new(RefCell::new(Scope {
+            data: HashMap::new(),
+            outer: Some(self.clone()),
+        })))
+    }
+
+    /// A handle that doesn't keep this scope alive, for values stored in
+    /// the scope itself that need to refer back to it.
+    pub fn downgrade(&self) -> WeakEnvironment {
+        WeakEnvironment(Rc::downgrade(&self.0))
+    }
+
+    pub fn define(&self, name: &str, value: MalDataType) {
+        self.0.borrow_mut().data.insert(name.to_owned(), value);
+    }
+
+    /// Looks `name` up in this scope, then in each outer scope in turn.
+    pub fn get(&self, name: &str) -> MalResult<MalDataType> {
+        let scope = self.0.borrow();
+        match (scope.data.get(name), &scope.outer) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, Some(outer)) => outer.get(name),
+            (None, None) => Err(MalError::SymbolNotFound(name.to_owned())),
+        }
+    }
+}
+
+/// See `MalEnvironment::downgrade`.
+#[derive(Clone)]
+pub struct WeakEnvironment(Weak<RefCell<Scope>>);
+
+impl WeakEnvironment {
+    /// The scope, unless it has already been dropped.
+    pub fn upgrade(&self) -> Option<MalEnvironment> {
+        self.0.upgrade().map(MalEnvironment)
+    }
+}
+
+impl Debug for MalEnvironment {
+    // Scopes can be reached again through closures they hold, so only the
+    // names are shown
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.0.borrow();
+        f.debug_struct("MalEnvironment")
+            .field("names", &scope.data.keys().collect::<Vec<_>>())
+            .field("outer", &scope.outer)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_look_up_through_outer_scopes() -> MalResult<()> {
+        let root = MalEnvironment::new();
+        root.define("a", MalDataType::Int(1));
+        let child = root.child();
+        child.define("b", MalDataType::Int(2));
+
+        assert_eq!(child.get("a")?, MalDataType::Int(1));
+        assert_eq!(child.get("b")?, MalDataType::Int(2));
+        assert!(matches!(child.get("+")?, MalDataType::Builtin(_)));
+        assert_eq!(root.get("b"), Err(MalError::SymbolNotFound("b".to_owned())));
+        Ok(())
+    }
+
+    #[test]
+    fn child_definitions_shadow_outer_ones() -> MalResult<()> {
+        let root = MalEnvironment::new();
+        root.define("a", MalDataType::Int(1));
+        let child = root.child();
+        child.define("a", MalDataType::Int(2));
+
+        assert_eq!(child.get("a")?, MalDataType::Int(2));
+        assert_eq!(root.get("a")?, MalDataType::Int(1));
+        Ok(())
     }
 }
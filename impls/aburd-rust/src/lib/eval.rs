@@ -0,0 +1,545 @@
+use std::rc::Rc;
+
+use crate::{environment::MalEnvironment, MalClosure, MalDataType, MalError, MalResult};
+
+/// What a special form or call leaves for `eval`: either a finished value,
+/// or a form in tail position to evaluate next in place of the current one.
+enum Step {
+    Done(MalDataType),
+    Continue(MalDataType, MalEnvironment),
+}
+
+/// Evaluates `ast` in `env`: symbols are looked up, collections evaluate
+/// their elements, and non-empty lists are function calls. Forms in tail
+/// position are evaluated by looping rather than recursing, so tail calls
+/// run in constant Rust stack.
+pub fn eval(ast: &MalDataType, env: &MalEnvironment) -> MalResult<MalDataType> {
+    if !matches!(ast, MalDataType::List(items, _) if !items.is_empty()) {
+        return eval_ast(ast, env);
+    }
+    let mut ast = ast.clone();
+    let mut env = env.clone();
+
+    loop {
+        ast = macroexpand(ast, &env)?;
+        let step = match &ast {
+            MalDataType::List(items, _) if !items.is_empty() => eval_list(items, &env)?,
+            _ => return eval_ast(&ast, &env),
+        };
+        match step {
+            Step::Done(value) => return Ok(value),
+            Step::Continue(next_ast, next_env) => {
+                ast = next_ast;
+                env = next_env;
+            }
+        }
+    }
+}
+
+fn eval_list(items: &[MalDataType], env: &MalEnvironment) -> MalResult<Step> {
+    if let MalDataType::Symbol(head) = &items[0] {
+        match head.as_str() {
+            "def!" => return eval_def(&items[1..], env).map(Step::Done),
+            "defmacro!" => return eval_defmacro(&items[1..], env).map(Step::Done),
+            "macroexpand" => {
+                let ast = one_arg("macroexpand", &items[1..])?;
+                return macroexpand(ast.clone(), env).map(Step::Done);
+            }
+            "let*" => return eval_let(&items[1..], env),
+            "do" => return eval_do(&items[1..], env),
+            "if" => return eval_if(&items[1..], env),
+            "fn*" => return eval_fn(&items[1..], env).map(Step::Done),
+            "quote" => return one_arg("quote", &items[1..]).cloned().map(Step::Done),
+            "quasiquoteexpand" => {
+                return one_arg("quasiquoteexpand", &items[1..])
+                    .map(|ast| Step::Done(quasiquote(ast)))
+            }
+            "quasiquote" => {
+                let ast = one_arg("quasiquote", &items[1..])?;
+                return Ok(Step::Continue(quasiquote(ast), env.clone()));
+            }
+            "try*" => return eval_try(&items[1..], env),
+            _ => {}
+        }
+    }
+
+    let items = items
+        .iter()
+        .map(|item| eval(item, env))
+        .collect::<MalResult<Vec<_>>>()?;
+    match &items[0] {
+        MalDataType::Closure(closure) => Ok(Step::Continue(
+            closure.body.clone(),
+            bind_args(closure, &items[1..])?,
+        )),
+        f => apply(f, &items[1..]).map(Step::Done),
+    }
+}
+
+fn one_arg<'a>(name: &str, args: &'a [MalDataType]) -> MalResult<&'a MalDataType> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(MalError::InvalidArgs(format!(
+            "{}: expected 1 argument, got {}",
+            name,
+            args.len()
+        ))),
+    }
+}
+
+/// Rewrites a quasiquoted form into the `cons`/`concat`/`vec` calls that
+/// build it, leaving `unquote`d forms to be evaluated.
+fn quasiquote(ast: &MalDataType) -> MalDataType {
+    match ast {
+        MalDataType::List(items, _) => match items.as_slice() {
+            [MalDataType::Symbol(head), form] if head == "unquote" => form.clone(),
+            _ => quasiquote_seq(items),
+        },
+        MalDataType::Vector(items, _) => call("vec", vec![quasiquote_seq(items)]),
+        MalDataType::Symbol(_) | MalDataType::HashMap(_, _) => call("quote", vec![ast.clone()]),
+        _ => ast.clone(),
+    }
+}
+
+fn quasiquote_seq(items: &[MalDataType]) -> MalDataType {
+    items
+        .iter()
+        .rev()
+        .fold(MalDataType::List(vec![], None), |acc, item| {
+            if let MalDataType::List(inner, _) = item {
+                if let [MalDataType::Symbol(head), form] = inner.as_slice() {
+                    if head == "splice-unquote" {
+                        return call("concat", vec![form.clone(), acc]);
+                    }
+                }
+            }
+            call("cons", vec![quasiquote(item), acc])
+        })
+}
+
+fn call(name: &str, args: Vec<MalDataType>) -> MalDataType {
+    let mut items = vec![MalDataType::Symbol(name.to_owned())];
+    items.extend(args);
+    MalDataType::List(items, None)
+}
+
+/// `(def! name value)` binds `value` in the current scope and returns it.
+fn eval_def(args: &[MalDataType], env: &MalEnvironment) -> MalResult<MalDataType> {
+    match args {
+        [MalDataType::Symbol(name), value] => {
+            let value = eval(value, env)?;
+            env.define(name, value.clone());
+            Ok(value)
+        }
+        _ => Err(MalError::InvalidArgs(
+            "def!: expected a symbol and a value".to_owned(),
+        )),
+    }
+}
+
+/// `(defmacro! name (fn* ...))` binds a macro version of the function.
+fn eval_defmacro(args: &[MalDataType], env: &MalEnvironment) -> MalResult<MalDataType> {
+    let [MalDataType::Symbol(name), value] = args else {
+        return Err(MalError::InvalidArgs(
+            "defmacro!: expected a symbol and a function".to_owned(),
+        ));
+    };
+    let MalDataType::Closure(closure) = eval(value, env)? else {
+        return Err(MalError::InvalidArgs(
+            "defmacro!: expected a function".to_owned(),
+        ));
+    };
+
+    let mac = MalDataType::Closure(Rc::new(MalClosure {
+        is_macro: true,
+        ..(*closure).clone()
+    }));
+    env.define(name, mac.clone());
+    Ok(mac)
+}
+
+/// The macro a list form calls, if its head is a symbol bound to one.
+fn macro_call(
+    ast: &MalDataType,
+    env: &MalEnvironment,
+) -> Option<(Rc<MalClosure>, Vec<MalDataType>)> {
+    let MalDataType::List(items, _) = ast else {
+        return None;
+    };
+    let Some(MalDataType::Symbol(head)) = items.first() else {
+        return None;
+    };
+    match env.get(head) {
+        Ok(MalDataType::Closure(closure)) if closure.is_macro => {
+            Some((closure, items[1..].to_vec()))
+        }
+        _ => None,
+    }
+}
+
+/// Expands `ast` until its head is no longer a macro call.
+fn macroexpand(mut ast: MalDataType, env: &MalEnvironment) -> MalResult<MalDataType> {
+    while let Some((mac, args)) = macro_call(&ast, env) {
+        ast = eval(&mac.body, &bind_args(&mac, &args)?)?;
+    }
+    Ok(ast)
+}
+
+/// `(let* (a 1 b a) body)` binds each name in a new scope, in order, so
+/// later bindings can see earlier ones.
+fn eval_let(args: &[MalDataType], env: &MalEnvironment) -> MalResult<Step> {
+    let (bindings, body) = match args {
+        [MalDataType::List(bindings, _) | MalDataType::Vector(bindings, _), body] => {
+            (bindings, body)
+        }
+        _ => {
+            return Err(MalError::InvalidArgs(
+                "let*: expected a binding list and a body".to_owned(),
+            ))
+        }
+    };
+    if bindings.len() % 2 != 0 {
+        return Err(MalError::InvalidArgs(
+            "let*: bindings need an even number of forms".to_owned(),
+        ));
+    }
+
+    let let_env = env.child();
+    for pair in bindings.chunks(2) {
+        let MalDataType::Symbol(name) = &pair[0] else {
+            return Err(MalError::InvalidArgs(format!(
+                "let*: cannot bind to {}",
+                pair[0]
+            )));
+        };
+        let value = eval(&pair[1], &let_env)?;
+        let_env.define(name, value);
+    }
+    Ok(Step::Continue(body.clone(), let_env))
+}
+
+/// `(do a b c)` evaluates each form in turn and returns the last value.
+fn eval_do(args: &[MalDataType], env: &MalEnvironment) -> MalResult<Step> {
+    let Some((last, init)) = args.split_last() else {
+        return Ok(Step::Done(MalDataType::Nil));
+    };
+    for form in init {
+        eval(form, env)?;
+    }
+    Ok(Step::Continue(last.clone(), env.clone()))
+}
+
+/// `(if cond then else?)`, where a missing else branch yields `nil`.
+fn eval_if(args: &[MalDataType], env: &MalEnvironment) -> MalResult<Step> {
+    let (cond, then, otherwise) = match args {
+        [cond, then] => (cond, then, None),
+        [cond, then, otherwise] => (cond, then, Some(otherwise)),
+        _ => {
+            return Err(MalError::InvalidArgs(
+                "if: expected a condition and one or two branches".to_owned(),
+            ))
+        }
+    };
+
+    let branch = if eval(cond, env)?.is_truthy() {
+        Some(then)
+    } else {
+        otherwise
+    };
+    Ok(branch.map_or(Step::Done(MalDataType::Nil), |form| {
+        Step::Continue(form.clone(), env.clone())
+    }))
+}
+
+/// `(fn* (a b & more) body)` creates a closure over `env`.
+fn eval_fn(args: &[MalDataType], env: &MalEnvironment) -> MalResult<MalDataType> {
+    let (params, body) = match args {
+        [MalDataType::List(params, _) | MalDataType::Vector(params, _), body] => (params, body),
+        _ => {
+            return Err(MalError::InvalidArgs(
+                "fn*: expected a parameter list and a body".to_owned(),
+            ))
+        }
+    };
+
+    let mut names = vec![];
+    for param in params {
+        match param {
+            MalDataType::Symbol(name) => names.push(name.to_owned()),
+            p => return Err(MalError::InvalidArgs(format!("fn*: cannot bind to {}", p))),
+        }
+    }
+    let rest = match names.iter().position(|name| name == "&") {
+        None => None,
+        Some(i) if i + 2 == names.len() => {
+            let rest = names.pop();
+            names.pop();
+            rest
+        }
+        Some(_) => {
+            return Err(MalError::InvalidArgs(
+                "fn*: & must be followed by exactly one parameter".to_owned(),
+            ))
+        }
+    };
+
+    Ok(MalDataType::Closure(Rc::new(MalClosure {
+        params: names,
+        rest,
+        body: body.clone(),
+        env: env.clone(),
+        is_macro: false,
+        meta: None,
+    })))
+}
+
+/// `(try* expr (catch* e handler))` evaluates `expr`, and if it fails,
+/// evaluates `handler` with the error bound to `e`.
+fn eval_try(args: &[MalDataType], env: &MalEnvironment) -> MalResult<Step> {
+    let (expr, name, handler) = match args {
+        [expr] => return Ok(Step::Continue(expr.clone(), env.clone())),
+        [expr, MalDataType::List(catch, _)] => match catch.as_slice() {
+            [MalDataType::Symbol(head), MalDataType::Symbol(name), handler] if head == "catch*" => {
+                (expr, name, handler)
+            }
+            _ => {
+                return Err(MalError::InvalidArgs(
+                    "catch*: expected a symbol and a handler".to_owned(),
+                ))
+            }
+        },
+        _ => {
+            return Err(MalError::InvalidArgs(
+                "try*: expected a form and a catch* clause".to_owned(),
+            ))
+        }
+    };
+
+    match eval(expr, env) {
+        Ok(value) => Ok(Step::Done(value)),
+        Err(e) => {
+            let catch_env = env.child();
+            catch_env.define(name, e.to_value());
+            Ok(Step::Continue(handler.clone(), catch_env))
+        }
+    }
+}
+
+/// Binds `args` to the closure's parameters in a new scope inside the one
+/// it was defined in.
+fn bind_args(closure: &MalClosure, args: &[MalDataType]) -> MalResult<MalEnvironment> {
+    let arity_ok = match closure.rest {
+        Some(_) => args.len() >= closure.params.len(),
+        None => args.len() == closure.params.len(),
+    };
+    if !arity_ok {
+        return Err(MalError::InvalidArgs(format!(
+            "expected {}{} arguments, got {}",
+            closure.params.len(),
+            if closure.rest.is_some() {
+                " or more"
+            } else {
+                ""
+            },
+            args.len()
+        )));
+    }
+
+    let fn_env = closure.env.child();
+    for (name, value) in closure.params.iter().zip(args) {
+        fn_env.define(name, value.clone());
+    }
+    if let Some(rest) = &closure.rest {
+        let rest_args = args[closure.params.len()..].to_vec();
+        fn_env.define(rest, MalDataType::List(rest_args, None));
+    }
+    Ok(fn_env)
+}
+
+fn eval_ast(ast: &MalDataType, env: &MalEnvironment) -> MalResult<MalDataType> {
+    match ast {
+        MalDataType::Symbol(s) => env.get(s),
+        MalDataType::Vector(items, _) => Ok(MalDataType::Vector(
+            items
+                .iter()
+                .map(|item| eval(item, env))
+                .collect::<MalResult<_>>()?,
+            None,
+        )),
+        MalDataType::HashMap(map, _) => Ok(MalDataType::HashMap(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), eval(v, env)?)))
+                .collect::<MalResult<_>>()?,
+            None,
+        )),
+        _ => Ok(ast.clone()),
+    }
+}
+
+pub fn apply(f: &MalDataType, args: &[MalDataType]) -> MalResult<MalDataType> {
+    match f {
+        MalDataType::Builtin(builtin) => (builtin.func)(args),
+        MalDataType::Closure(closure) => eval(&closure.body, &bind_args(closure, args)?),
+        _ => Err(MalError::NotCallable(f.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::read_str;
+
+    /// Evaluates `forms` in turn in one fresh environment, so later forms
+    /// see earlier definitions, and prints the last result.
+    fn rep_all(forms: &[&str]) -> MalResult<String> {
+        let env = MalEnvironment::new();
+        let mut value = MalDataType::Nil;
+        for form in forms {
+            value = eval(&read_str(form).unwrap(), &env)?;
+        }
+        Ok(value.to_string())
+    }
+
+    fn rep(s: &str) -> String {
+        rep_all(&[s]).unwrap_or_else(|e| e.to_string())
+    }
+
+    #[test]
+    fn can_eval_arithmetic() {
+        assert_eq!(rep("(+ 1 (* 2 3))"), "7");
+        assert_eq!(rep("[1 (- 3 1) (/ 9 3)]"), "[1 2 3]");
+        assert_eq!(rep(r#"{"a" (+ 1 2)}"#), r#"{"a" 3}"#);
+        assert_eq!(rep("()"), "()");
+    }
+
+    #[test]
+    fn can_def_and_let() {
+        assert_eq!(rep_all(&["(def! x 3)"]), Ok("3".to_owned()));
+        assert_eq!(
+            rep_all(&["(let* [x 1 y (+ x 1)] (* x y))"]),
+            Ok("2".to_owned())
+        );
+        assert_eq!(
+            rep_all(&["(def! x 3)", "(let* (z x) z)"]),
+            Ok("3".to_owned())
+        );
+        assert_eq!(
+            rep_all(&["(def! x 3)", "(let* (x 1) x)", "x"]),
+            Ok("3".to_owned())
+        );
+        assert!(rep_all(&["(def! w (abc))"]).is_err());
+        assert_eq!(
+            rep_all(&["(try* (def! w (abc)) (catch* e nil))", "w"]),
+            Err(MalError::SymbolNotFound("w".to_owned()))
+        );
+    }
+
+    #[test]
+    fn can_call_closures() {
+        assert_eq!(
+            rep_all(&[
+                "(def! gen-plus (fn* (x) (fn* (y) (+ x y))))",
+                "((gen-plus 5) 7)"
+            ]),
+            Ok("12".to_owned())
+        );
+        assert_eq!(rep("((fn* (a & more) more) 1 2 3)"), "(2 3)");
+        assert_eq!(rep("((fn* [& more] more))"), "()");
+        assert_eq!(rep("(if nil 1 (do 2 3))"), "3");
+        assert_eq!(rep("(if false 1)"), "nil");
+        assert!(rep_all(&["((fn* (a) a))"]).is_err());
+    }
+
+    #[test]
+    fn tail_calls_do_not_grow_the_stack() {
+        assert_eq!(
+            rep_all(&[
+                "(def! sum2 (fn* (n acc) (if (= n 0) acc (sum2 (- n 1) (+ n acc)))))",
+                "(sum2 10000 0)",
+            ]),
+            Ok("50005000".to_owned())
+        );
+        assert_eq!(
+            rep_all(&[
+                "(def! count-down (fn* (n) (let* [m (- n 1)] (do nil (if (> m 0) (count-down m) m)))))",
+                "(count-down 10000)",
+            ]),
+            Ok("0".to_owned())
+        );
+    }
+
+    #[test]
+    fn eval_runs_in_the_root_scope() {
+        assert_eq!(
+            rep_all(&["(def! a 1)", r#"(let* (a 2) (eval (read-string "a")))"#]),
+            Ok("1".to_owned())
+        );
+        assert_eq!(
+            rep_all(&[r#"(let* (b 12) (do (eval (read-string "(def! aa 7)")) aa))"#]),
+            Ok("7".to_owned())
+        );
+        assert_eq!(
+            rep_all(&[r#"(let* (b 12) (eval (read-string "(def! aa 7)")))"#, "aa"]),
+            Ok("7".to_owned())
+        );
+    }
+
+    #[test]
+    fn eval_and_load_file_are_functions() {
+        assert_eq!(
+            rep_all(&["(def! f eval)", "(f '(+ 1 2))"]),
+            Ok("3".to_owned())
+        );
+        assert_eq!(rep("(map eval '((+ 1 2) (* 2 3)))"), "(3 6)");
+        assert_eq!(rep("(fn? load-file)"), "true");
+    }
+
+    #[test]
+    fn can_quasiquote() {
+        let c = "(def! c '(1 \"b\" \"d\"))";
+        assert_eq!(
+            rep_all(&[c, "`(1 ~c 3)"]),
+            Ok(r#"(1 (1 "b" "d") 3)"#.to_owned())
+        );
+        assert_eq!(
+            rep_all(&[c, "`(1 ~@c 3)"]),
+            Ok(r#"(1 1 "b" "d" 3)"#.to_owned())
+        );
+        assert_eq!(
+            rep_all(&[c, "`[1 ~@c [a]]"]),
+            Ok(r#"[1 1 "b" "d" [a]]"#.to_owned())
+        );
+        assert_eq!(
+            rep("(quasiquoteexpand [a ~b])"),
+            "(vec (cons (quote a) (cons b ())))"
+        );
+    }
+
+    #[test]
+    fn can_expand_macros() {
+        let unless = "(defmacro! unless (fn* (pred a b) `(if ~pred ~b ~a)))";
+        assert_eq!(rep_all(&[unless, "(unless false 7 8)"]), Ok("7".to_owned()));
+        assert_eq!(
+            rep_all(&[unless, "(macroexpand (unless 2 3 4))"]),
+            Ok("(if 2 4 3)".to_owned())
+        );
+        assert_eq!(rep("(macroexpand (+ 1 2))"), "(+ 1 2)");
+    }
+
+    #[test]
+    fn can_catch_errors() {
+        assert_eq!(rep("(try* (throw {:a 1}) (catch* e (get e :a)))"), "1");
+        assert_eq!(rep("(try* (abc 1 2) (catch* e e))"), r#""'abc' not found""#);
+        assert_eq!(rep("(try* 123 (catch* e 0))"), "123");
+        assert_eq!(
+            rep_all(&[r#"(throw "oops")"#]),
+            Err(MalError::Thrown(MalDataType::String("oops".to_owned())))
+        );
+    }
+
+    #[test]
+    fn reports_eval_errors() {
+        assert_eq!(rep("(abc 1 2)"), "'abc' not found");
+        assert_eq!(rep("(1 2)"), "1 is not a function");
+    }
+}
@@ -1,23 +1,151 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+use environment::MalEnvironment;
+use read::MalReaderError;
+
+pub mod core;
 pub mod environment;
+pub mod eval;
+pub mod print;
 pub mod read;
+pub mod repl;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub enum MalDataType {
     Nil,
     Boolean(bool),
-    Int(usize),
+    Int(i64),
+    Float(f64),
     String(String),
     Keyword(String),
-    Vector(Vec<MalToken>),
-    List(Vec<MalToken>),
+    Vector(Vec<MalDataType>, MalMeta),
+    List(Vec<MalDataType>, MalMeta),
+    HashMap(HashMap<MalMapKey, MalDataType>, MalMeta),
     Symbol(String),
+    Builtin(MalBuiltin),
+    Closure(Rc<MalClosure>),
+    Atom(Rc<RefCell<MalDataType>>),
+}
+
+impl PartialEq for MalDataType {
+    /// Structural equality, except that lists and vectors with equal
+    /// elements are equal to each other. Metadata is ignored.
+    fn eq(&self, other: &Self) -> bool {
+        use MalDataType::*;
+
+        match (self, other) {
+            (List(a, _) | Vector(a, _), List(b, _) | Vector(b, _)) => a == b,
+            (Nil, Nil) => true,
+            (Boolean(a), Boolean(b)) => a == b,
+            (Int(a), Int(b)) => a == b,
+            (Float(a), Float(b)) => a == b,
+            (String(a), String(b)) | (Keyword(a), Keyword(b)) | (Symbol(a), Symbol(b)) => a == b,
+            (HashMap(a, _), HashMap(b, _)) => a == b,
+            (Builtin(a), Builtin(b)) => a == b,
+            (Closure(a), Closure(b)) => Rc::ptr_eq(a, b),
+            (Atom(a), Atom(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl MalDataType {
+    /// Only `nil` and `false` are falsy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, MalDataType::Nil | MalDataType::Boolean(false))
+    }
+}
+
+/// Hash-map keys are restricted to strings and keywords, as in the mal spec.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum MalMapKey {
+    String(String),
+    Keyword(String),
+}
+
+/// The value `with-meta` attaches to a collection or function, if any.
+pub type MalMeta = Option<Rc<MalDataType>>;
+
+pub type MalBuiltinFn = dyn Fn(&[MalDataType]) -> MalResult<MalDataType>;
+
+/// A function implemented in Rust, such as the arithmetic in `core`.
+/// Most are plain functions, but `eval` and `load-file` close over the
+/// root environment. `meta` is set by `with-meta`.
+#[derive(Clone)]
+pub struct MalBuiltin {
+    pub name: &'static str,
+    pub func: Rc<MalBuiltinFn>,
+    pub meta: MalMeta,
+}
+
+impl PartialEq for MalBuiltin {
+    // Builtin names are unique, and functions can't be compared reliably
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl std::fmt::Debug for MalBuiltin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MalBuiltin")
+            .field("name", &self.name)
+            .field("meta", &self.meta)
+            .finish()
+    }
+}
+
+/// A function defined in mal with `fn*`. It keeps the scope it was
+/// defined in, and `rest` names the parameter after `&`, if any. Macros
+/// are closures with `is_macro` set by `defmacro!`.
+#[derive(Debug, Clone)]
+pub struct MalClosure {
+    pub params: Vec<String>,
+    pub rest: Option<String>,
+    pub body: MalDataType,
+    pub env: MalEnvironment,
+    pub is_macro: bool,
+    pub meta: MalMeta,
 }
 
 #[derive(Debug, PartialEq, Clone)]
-pub enum MalToken {
-    OpenParen,
-    CloseParen,
-    OpenBracket,
-    CloseBracket,
-    Data(MalDataType),
+pub enum MalError {
+    SymbolNotFound(String),
+    InvalidArgs(String),
+    NotCallable(String),
+    Read(MalReaderError),
+    Io(String),
+    /// A value raised by `throw`.
+    Thrown(MalDataType),
 }
+
+impl MalError {
+    /// The value `catch*` binds: thrown values as they are, and any other
+    /// error as its message.
+    pub fn to_value(&self) -> MalDataType {
+        match self {
+            MalError::Thrown(value) => value.clone(),
+            e => MalDataType::String(e.to_string()),
+        }
+    }
+}
+
+impl Display for MalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MalError::SymbolNotFound(s) => write!(f, "'{}' not found", s),
+            MalError::InvalidArgs(msg) => f.write_str(msg),
+            MalError::NotCallable(s) => write!(f, "{} is not a function", s),
+            MalError::Read(e) => e.fmt(f),
+            MalError::Io(msg) => f.write_str(msg),
+            MalError::Thrown(value) => value.fmt(f),
+        }
+    }
+}
+
+impl From<MalReaderError> for MalError {
+    fn from(e: MalReaderError) -> Self {
+        MalError::Read(e)
+    }
+}
+
+pub type MalResult<T> = Result<T, MalError>;
@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use crate::{MalDataType, MalMapKey};
+
+/// Renders `data` as text. Readable output quotes and re-escapes strings so
+/// it can be fed back to the reader (`pr-str`, `prn`); otherwise strings are
+/// printed raw (`str`, `println`).
+pub fn pr_str(data: &MalDataType, print_readably: bool) -> String {
+    match data {
+        MalDataType::Keyword(s) => format!(":{}", &s[1..]),
+        MalDataType::Nil => "nil".to_owned(),
+        MalDataType::Boolean(b) => b.to_string(),
+        MalDataType::Int(n) => n.to_string(),
+        // Debug keeps the `.0` on whole floats so they read back as floats
+        MalDataType::Float(n) if n.is_finite() => format!("{:?}", n),
+        MalDataType::Float(n) if n.is_nan() => "##NaN".to_owned(),
+        MalDataType::Float(n) if *n > 0.0 => "##Inf".to_owned(),
+        MalDataType::Float(_) => "##-Inf".to_owned(),
+        MalDataType::String(s) => pr_string(s, print_readably),
+        MalDataType::Symbol(s) => s.to_string(),
+        MalDataType::Vector(items, _) => format!("[{}]", pr_seq(items, print_readably)),
+        MalDataType::List(items, _) => format!("({})", pr_seq(items, print_readably)),
+        MalDataType::HashMap(map, _) => {
+            let content = map
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        "{} {}",
+                        pr_map_key(k, print_readably),
+                        pr_str(v, print_readably)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{{{}}}", content)
+        }
+        MalDataType::Builtin(builtin) => format!("#<builtin {}>", builtin.name),
+        MalDataType::Closure(closure) if closure.is_macro => "#<macro>".to_owned(),
+        MalDataType::Closure(_) => "#<function>".to_owned(),
+        MalDataType::Atom(atom) => format!("(atom {})", pr_str(&atom.borrow(), print_readably)),
+    }
+}
+
+fn pr_seq(items: &[MalDataType], print_readably: bool) -> String {
+    items
+        .iter()
+        .map(|v| pr_str(v, print_readably))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn pr_map_key(key: &MalMapKey, print_readably: bool) -> String {
+    match key {
+        MalMapKey::String(s) => pr_string(s, print_readably),
+        MalMapKey::Keyword(s) => format!(":{}", &s[1..]),
+    }
+}
+
+fn pr_string(s: &str, print_readably: bool) -> String {
+    if !print_readably {
+        return s.to_owned();
+    }
+
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+impl Display for MalDataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&pr_str(self, true))
+    }
+}
+
+impl Display for MalMapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&pr_map_key(self, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_print_strings_readably_and_raw() {
+        let data = MalDataType::List(vec![MalDataType::String("a\"b\\c\nd".to_owned())], None);
+        assert_eq!(pr_str(&data, true), r#"("a\"b\\c\nd")"#.to_owned());
+        assert_eq!(pr_str(&data, false), "(a\"b\\c\nd)".to_owned());
+    }
+
+    #[test]
+    fn can_print_non_finite_floats() {
+        for (f, expected) in [
+            (f64::INFINITY, "##Inf"),
+            (f64::NEG_INFINITY, "##-Inf"),
+            (f64::NAN, "##NaN"),
+            (2.0, "2.0"),
+        ] {
+            assert_eq!(pr_str(&MalDataType::Float(f), true), expected.to_owned());
+        }
+    }
+}
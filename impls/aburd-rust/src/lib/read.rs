@@ -1,252 +1,510 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
-use crate::{environment::MalEnvironment, MalDataType, MalToken};
+use crate::{MalDataType, MalMapKey};
 use regex::Regex;
 
-#[derive(Debug)]
-pub enum MalReaderError {
+#[derive(Debug, PartialEq, Clone)]
+pub enum MalReaderErrorKind {
     LexingFailure(String),
     IllegalToken(String),
     IllegalString(String),
     IllegalSymbol(String),
     UnterminatedList,
+    OddHashMapEntries(usize),
+    IllegalHashMapKey(String),
+    MissingMacroForm(String),
+    UnexpectedToken(String),
+    NumberOutOfRange(String),
+    UnfinishedMacro(String),
+    NoForm,
+    TrailingInput(String),
+}
+
+impl Display for MalReaderErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MalReaderErrorKind::LexingFailure(e) => write!(f, "lexing failure: {}", e),
+            MalReaderErrorKind::IllegalToken(t) => write!(f, "illegal token '{}'", t),
+            MalReaderErrorKind::IllegalString(s) => {
+                write!(f, "unbalanced string {}, expected '\"' before EOF", s)
+            }
+            MalReaderErrorKind::IllegalSymbol(s) => write!(f, "illegal symbol '{}'", s),
+            MalReaderErrorKind::UnterminatedList => {
+                f.write_str("unbalanced, this is never closed before EOF")
+            }
+            MalReaderErrorKind::OddHashMapEntries(n) => {
+                write!(f, "hash map needs an even number of forms, found {}", n)
+            }
+            MalReaderErrorKind::IllegalHashMapKey(k) => {
+                write!(f, "hash map keys must be strings or keywords, found {}", k)
+            }
+            MalReaderErrorKind::MissingMacroForm(m) => {
+                write!(f, "reader macro '{}' is not followed by a form", m)
+            }
+            MalReaderErrorKind::UnexpectedToken(t) => write!(f, "unbalanced, unexpected '{}'", t),
+            MalReaderErrorKind::NumberOutOfRange(n) => write!(f, "number {} is out of range", n),
+            MalReaderErrorKind::UnfinishedMacro(m) => {
+                write!(f, "reader macro '{}' reached EOF before its form", m)
+            }
+            MalReaderErrorKind::NoForm => f.write_str("no form to read"),
+            MalReaderErrorKind::TrailingInput(t) => {
+                write!(f, "expected a single form, found '{}' after it", t)
+            }
+        }
+    }
+}
+
+/// Where in the source a reader error happened. `line` and `column` are
+/// 1-based, and `column` counts characters rather than bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MalSourcePos {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl MalSourcePos {
+    pub fn new(src: &str, offset: usize) -> Self {
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        MalSourcePos {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MalReaderError {
+    pub kind: MalReaderErrorKind,
+    pub pos: MalSourcePos,
+}
+
+impl MalReaderError {
+    /// True when reading failed only because the input ended early, so more
+    /// input could still complete the form.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.kind,
+            MalReaderErrorKind::UnterminatedList
+                | MalReaderErrorKind::IllegalString(_)
+                | MalReaderErrorKind::UnfinishedMacro(_)
+        )
+    }
+
+    pub fn new(kind: MalReaderErrorKind, src: &str, offset: usize) -> Self {
+        MalReaderError {
+            kind,
+            pos: MalSourcePos::new(src, offset),
+        }
+    }
+
+    /// Renders the error followed by the offending source line and a caret
+    /// under the error position.
+    pub fn annotate(&self, src: &str) -> String {
+        let line_start = src[..self.pos.offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = src[line_start..].lines().next().unwrap_or("");
+        // Keep tabs so the caret lines up with the source line
+        let padding = src[line_start..self.pos.offset]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        format!("{}\n{}\n{}^", self, line, padding)
+    }
 }
 
 impl Display for MalReaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(format!("MalReaderError: {:?}", self).as_str())
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.pos.line, self.pos.column, self.kind
+        )
     }
 }
 
 pub type MalReaderResult<T> = Result<T, MalReaderError>;
 
-impl MalDataType {
-    pub fn to_string(&self) -> String {
+impl MalMapKey {
+    pub fn from_data(data: &MalDataType) -> Option<MalMapKey> {
+        match data {
+            MalDataType::String(s) => Some(MalMapKey::String(s.to_owned())),
+            MalDataType::Keyword(s) => Some(MalMapKey::Keyword(s.to_owned())),
+            _ => None,
+        }
+    }
+
+    pub fn to_data(&self) -> MalDataType {
         match self {
-            MalDataType::Keyword(s) => format!(":{}", s[1..].to_owned()),
-            MalDataType::Nil => "nil".to_owned(),
-            MalDataType::Boolean(b) => b.to_string(),
-            MalDataType::Int(n) => n.to_string(),
-            MalDataType::String(s) => s.to_string(),
-            MalDataType::Symbol(s) => s.to_string(),
-            MalDataType::Vector(tokens) => {
-                let content = tokens
-                    .iter()
-                    .map(|v| v.to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                format!("[{}]", content)
-            }
-            MalDataType::List(tokens) => {
-                let content = tokens
-                    .iter()
-                    .map(|v| v.to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                format!("({})", content)
-            }
+            MalMapKey::String(s) => MalDataType::String(s.to_owned()),
+            MalMapKey::Keyword(s) => MalDataType::Keyword(s.to_owned()),
         }
     }
 }
 
-impl MalToken {
-    pub fn to_string(&self) -> String {
+/// Lexical tokens. Structural tokens never leave the reader; only the
+/// `MalDataType` tree built from them does.
+#[derive(Debug, PartialEq, Clone)]
+enum MalToken {
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    OpenBrace,
+    CloseBrace,
+    Quote,
+    Quasiquote,
+    Unquote,
+    SpliceUnquote,
+    Deref,
+    WithMeta,
+    Discard,
+    Data(MalDataType),
+}
+
+impl Display for MalToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            MalToken::OpenParen => "(".to_owned(),
-            MalToken::CloseParen => ")".to_owned(),
-            MalToken::OpenBracket => "[".to_owned(),
-            MalToken::CloseBracket => "]".to_owned(),
-            MalToken::Data(d) => d.to_string(),
+            MalToken::OpenParen => f.write_str("("),
+            MalToken::CloseParen => f.write_str(")"),
+            MalToken::OpenBracket => f.write_str("["),
+            MalToken::CloseBracket => f.write_str("]"),
+            MalToken::OpenBrace => f.write_str("{"),
+            MalToken::CloseBrace => f.write_str("}"),
+            MalToken::Quote => f.write_str("'"),
+            MalToken::Quasiquote => f.write_str("`"),
+            MalToken::Unquote => f.write_str("~"),
+            MalToken::SpliceUnquote => f.write_str("~@"),
+            MalToken::Deref => f.write_str("@"),
+            MalToken::WithMeta => f.write_str("^"),
+            MalToken::Discard => f.write_str("#_"),
+            MalToken::Data(d) => d.fmt(f),
         }
     }
+}
 
-    pub fn from_str(s: &str) -> MalReaderResult<MalToken> {
+impl FromStr for MalToken {
+    type Err = MalReaderErrorKind;
+
+    fn from_str(s: &str) -> Result<MalToken, MalReaderErrorKind> {
         match s {
             "(" => Ok(MalToken::OpenParen),
             ")" => Ok(MalToken::CloseParen),
             "[" => Ok(MalToken::OpenBracket),
             "]" => Ok(MalToken::CloseBracket),
+            "{" => Ok(MalToken::OpenBrace),
+            "}" => Ok(MalToken::CloseBrace),
+            "'" => Ok(MalToken::Quote),
+            "`" => Ok(MalToken::Quasiquote),
+            "~" => Ok(MalToken::Unquote),
+            "~@" => Ok(MalToken::SpliceUnquote),
+            "@" => Ok(MalToken::Deref),
+            "^" => Ok(MalToken::WithMeta),
+            "#_" => Ok(MalToken::Discard),
             "nil" => Ok(MalToken::Data(MalDataType::Nil)),
             "true" => Ok(MalToken::Data(MalDataType::Boolean(true))),
             "false" => Ok(MalToken::Data(MalDataType::Boolean(false))),
-            s if s.starts_with(":") => {
+            "##Inf" => Ok(MalToken::Data(MalDataType::Float(f64::INFINITY))),
+            "##-Inf" => Ok(MalToken::Data(MalDataType::Float(f64::NEG_INFINITY))),
+            "##NaN" => Ok(MalToken::Data(MalDataType::Float(f64::NAN))),
+            s if s.starts_with(':') => {
                 if s.starts_with("::") {
-                    return Err(MalReaderError::IllegalToken(s.to_owned()));
+                    return Err(MalReaderErrorKind::IllegalToken(s.to_owned()));
                 }
 
                 Ok(MalToken::Data(MalDataType::Keyword(s.to_owned())))
             }
-            s if s.chars().all(|c| c.is_digit(10)) => Ok(MalToken::Data(MalDataType::Int(
-                s.parse::<usize>().unwrap(),
-            ))),
-            s if s.starts_with("\"") => {
-                if s.len() < 2 || !s.ends_with("\"") {
-                    return Err(MalReaderError::IllegalString(s.to_owned()));
-                }
-
-                Ok(MalToken::Data(MalDataType::String(s.to_string())))
-            }
+            s if s.starts_with('"') => Ok(MalToken::Data(MalDataType::String(unescape(s)?))),
             _ => {
+                if let Some(number) = parse_number(s) {
+                    return number.map(MalToken::Data);
+                }
                 // Symbols must not contain certain characters
-                if s.contains("\"") {
-                    return Err(MalReaderError::IllegalSymbol(s.to_owned()));
+                if s.contains('"') {
+                    return Err(MalReaderErrorKind::IllegalSymbol(s.to_owned()));
                 }
                 // Illegal symbol starting character should panic
-                if s.chars().next().unwrap().is_digit(10) {
-                    return Err(MalReaderError::IllegalSymbol(s.to_owned()));
+                if s.chars().next().unwrap().is_ascii_digit() {
+                    return Err(MalReaderErrorKind::IllegalSymbol(s.to_owned()));
                 }
 
-                return Ok(MalToken::Data(MalDataType::Symbol(s.to_owned())));
+                Ok(MalToken::Data(MalDataType::Symbol(s.to_owned())))
             }
         }
     }
 }
 
+/// Strips the quotes from a string literal and decodes its escapes.
+/// Anything after a backslash other than `n` stands for itself.
+fn unescape(s: &str) -> Result<String, MalReaderErrorKind> {
+    let mut decoded = String::with_capacity(s.len());
+    let mut chars = s[1..].chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Ok(decoded),
+            '\\' => match chars.next() {
+                Some('n') => decoded.push('\n'),
+                Some(escaped) => decoded.push(escaped),
+                None => break,
+            },
+            c => decoded.push(c),
+        }
+    }
+
+    Err(MalReaderErrorKind::IllegalString(s.to_owned()))
+}
+
+/// Parses `s` as a number if it looks like one: an optionally signed run of
+/// digits, optionally followed by a fraction and/or an exponent. Literals
+/// that don't fit in an `i64` or `f64` are reported instead of truncated.
+fn parse_number(s: &str) -> Option<Result<MalDataType, MalReaderErrorKind>> {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    if unsigned.chars().all(|c| c.is_ascii_digit()) {
+        return Some(
+            s.parse::<i64>()
+                .map(MalDataType::Int)
+                .map_err(|_| MalReaderErrorKind::NumberOutOfRange(s.to_owned())),
+        );
+    }
+
+    match s.parse::<f64>() {
+        Ok(f) if f.is_finite() => Some(Ok(MalDataType::Float(f))),
+        Ok(_) => Some(Err(MalReaderErrorKind::NumberOutOfRange(s.to_owned()))),
+        Err(_) => None,
+    }
+}
+
 #[derive(Debug)]
-struct Reader {
-    tokens: Vec<MalToken>,
+struct Reader<'a> {
+    src: &'a str,
+    tokens: Vec<(usize, MalToken)>,
     pos: usize,
 }
 
-impl Reader {
-    fn new<'a>(tokens: Vec<MalToken>) -> Self {
-        Reader { tokens, pos: 0 }
+impl<'a> Reader<'a> {
+    fn new(src: &'a str, tokens: Vec<(usize, MalToken)>) -> Self {
+        Reader {
+            src,
+            tokens,
+            pos: 0,
+        }
     }
 }
 
-impl Reader {
-    pub fn peek(&self) -> MalReaderResult<&MalToken> {
-        if let Some(token) = self.tokens.get(self.pos) {
-            return Ok(token);
-        }
-        Err(MalReaderError::UnterminatedList)
+impl Reader<'_> {
+    fn error(&self, kind: MalReaderErrorKind, offset: usize) -> MalReaderError {
+        MalReaderError::new(kind, self.src, offset)
     }
 
-    pub fn read_list(&mut self) -> MalReaderResult<MalToken> {
-        let mut tokens = vec![];
+    fn next(&mut self) -> MalReaderResult<(usize, MalToken)> {
+        let (offset, token) = self.peek()?;
+        let token = token.clone();
+        self.pos += 1;
+        Ok((offset, token))
+    }
 
-        while let Ok(token) = self.read_form() {
-            let is_list_end = token == MalToken::CloseParen;
-            if is_list_end {
-                return Ok(MalToken::Data(MalDataType::List(tokens)));
-            }
-            tokens.push(token);
-            self.pos += 1;
+    fn peek(&self) -> MalReaderResult<(usize, &MalToken)> {
+        self.tokens
+            .get(self.pos)
+            .map(|(offset, token)| (*offset, token))
+            .ok_or_else(|| self.error(MalReaderErrorKind::UnterminatedList, self.src.len()))
+    }
+
+    /// Reads forms until `end` is consumed, pairing each with its offset.
+    /// Running out of input is reported at `start`, the opening delimiter.
+    fn read_seq(
+        &mut self,
+        start: usize,
+        end: MalToken,
+    ) -> MalReaderResult<Vec<(usize, MalDataType)>> {
+        let mut items = vec![];
+
+        loop {
+            self.skip_discarded()?;
+            let offset = match self.peek() {
+                Ok((_, token)) if token == &end => {
+                    self.pos += 1;
+                    return Ok(items);
+                }
+                Ok((offset, _)) => offset,
+                Err(_) => return Err(self.error(MalReaderErrorKind::UnterminatedList, start)),
+            };
+            items.push((offset, self.read_form()?));
         }
+    }
 
-        Err(MalReaderError::UnterminatedList)
+    fn read_list(&mut self, start: usize, end: MalToken) -> MalReaderResult<Vec<MalDataType>> {
+        Ok(self
+            .read_seq(start, end)?
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect())
     }
 
-    pub fn read_vector(&mut self) -> MalReaderResult<MalToken> {
-        let mut tokens = vec![];
+    fn read_hash_map(&mut self, start: usize) -> MalReaderResult<MalDataType> {
+        let items = self.read_seq(start, MalToken::CloseBrace)?;
+        if items.len() % 2 != 0 {
+            return Err(self.error(MalReaderErrorKind::OddHashMapEntries(items.len()), start));
+        }
 
-        while let Ok(token) = self.read_form() {
-            let is_list_end = token == MalToken::CloseBracket;
+        let mut map = HashMap::new();
+        for pair in items.chunks(2) {
+            let (key_offset, key) = &pair[0];
+            let key = MalMapKey::from_data(key).ok_or_else(|| {
+                self.error(
+                    MalReaderErrorKind::IllegalHashMapKey(key.to_string()),
+                    *key_offset,
+                )
+            })?;
+            map.insert(key, pair[1].1.clone());
+        }
+        Ok(MalDataType::HashMap(map, None))
+    }
 
-            if is_list_end {
-                return Ok(MalToken::Data(MalDataType::Vector(tokens)));
-            }
-            tokens.push(token);
-            self.pos += 1;
+    /// Reads the form following a reader macro token and wraps it, e.g.
+    /// `'(1 2)` becomes `(quote (1 2))` and `^{"a" 1} [1]` becomes
+    /// `(with-meta [1] {"a" 1})`.
+    fn read_macro(
+        &mut self,
+        name: &str,
+        start: usize,
+        macro_token: &MalToken,
+    ) -> MalReaderResult<MalDataType> {
+        let symbol = MalDataType::Symbol(name.to_owned());
+        let form = self.read_macro_form(start, macro_token)?;
+        if macro_token == &MalToken::WithMeta {
+            let target = self.read_macro_form(start, macro_token)?;
+            return Ok(MalDataType::List(vec![symbol, target, form], None));
         }
 
-        Err(MalReaderError::UnterminatedList)
+        Ok(MalDataType::List(vec![symbol, form], None))
     }
 
-    pub fn read_atom(&self) -> MalReaderResult<MalToken> {
-        Ok(self.peek()?.clone())
+    fn read_macro_form(
+        &mut self,
+        start: usize,
+        macro_token: &MalToken,
+    ) -> MalReaderResult<MalDataType> {
+        match self.peek() {
+            Ok((_, MalToken::CloseParen | MalToken::CloseBracket | MalToken::CloseBrace)) => {
+                Err(self.error(
+                    MalReaderErrorKind::MissingMacroForm(macro_token.to_string()),
+                    start,
+                ))
+            }
+            Ok(_) => self.read_form(),
+            Err(_) => Err(self.error(
+                MalReaderErrorKind::UnfinishedMacro(macro_token.to_string()),
+                start,
+            )),
+        }
     }
 
-    pub fn read_form(&mut self) -> MalReaderResult<MalToken> {
-        let token = self.peek()?;
-        if token == &MalToken::OpenParen {
-            self.pos += 1;
-            self.read_list()
-        } else if token == &MalToken::OpenBracket {
+    /// Consumes any `#_` tokens along with the form each one discards.
+    fn skip_discarded(&mut self) -> MalReaderResult<()> {
+        while let Ok((start, MalToken::Discard)) = self.peek() {
             self.pos += 1;
-            self.read_vector()
-        } else {
-            self.read_atom()
+            self.read_macro_form(start, &MalToken::Discard)?;
+        }
+        Ok(())
+    }
+
+    fn read_form(&mut self) -> MalReaderResult<MalDataType> {
+        self.skip_discarded()?;
+        let (offset, token) = self.next()?;
+        match token {
+            MalToken::OpenParen => Ok(MalDataType::List(
+                self.read_list(offset, MalToken::CloseParen)?,
+                None,
+            )),
+            MalToken::OpenBracket => Ok(MalDataType::Vector(
+                self.read_list(offset, MalToken::CloseBracket)?,
+                None,
+            )),
+            MalToken::OpenBrace => self.read_hash_map(offset),
+            MalToken::CloseParen | MalToken::CloseBracket | MalToken::CloseBrace => Err(self
+                .error(
+                    MalReaderErrorKind::UnexpectedToken(token.to_string()),
+                    offset,
+                )),
+            MalToken::Quote => self.read_macro("quote", offset, &token),
+            MalToken::Quasiquote => self.read_macro("quasiquote", offset, &token),
+            MalToken::Unquote => self.read_macro("unquote", offset, &token),
+            MalToken::SpliceUnquote => self.read_macro("splice-unquote", offset, &token),
+            MalToken::Deref => self.read_macro("deref", offset, &token),
+            MalToken::WithMeta => self.read_macro("with-meta", offset, &token),
+            MalToken::Discard => unreachable!("skip_discarded consumes every #_"),
+            MalToken::Data(d) => Ok(d),
         }
     }
 }
 
-fn lexer(s: &str) -> MalReaderResult<Vec<&str>> {
-    let re = Regex::new(r#"[\s,]*(~@|[\[\]{}()'`~^@]|"(?:\\.|[^\\"])*"?|;.*|[^\s\[\]{}('"`,;)]*)"#)
-        .map_err(|e| MalReaderError::LexingFailure(e.to_string()))?;
+/// Splits `s` into lexemes, each paired with its byte offset in `s`.
+/// Comments are dropped here so the reader never sees them.
+fn lexer(s: &str) -> MalReaderResult<Vec<(usize, &str)>> {
+    let re = Regex::new(
+        r#"[\s,]*(~@|#_|[\[\]{}()'`~^@]|"(?:\\.|[^\\"])*"?|;.*|[^\s\[\]{}('"`,;)]*)"#,
+    )
+    .map_err(|e| MalReaderError::new(MalReaderErrorKind::LexingFailure(e.to_string()), s, 0))?;
 
     Ok(re
-        .captures_iter(s.trim())
-        .map(|c| {
-            let (_, [s]) = c.extract();
-            s
-        })
-        .filter(|s| !s.is_empty())
+        .captures_iter(s)
+        .filter_map(|c| c.get(1))
+        .filter(|m| !m.is_empty() && !m.as_str().starts_with(';'))
+        .map(|m| (m.start(), m.as_str()))
         .collect())
 }
 
-fn tokenize(lexemes: &[&str]) -> MalReaderResult<Vec<MalToken>> {
+fn tokenize(src: &str, lexemes: &[(usize, &str)]) -> MalReaderResult<Vec<(usize, MalToken)>> {
     let mut tokens = vec![];
-    for l in lexemes {
-        let token = MalToken::from_str(l)?;
-        tokens.push(token);
+    for (offset, l) in lexemes {
+        let token = l
+            .parse::<MalToken>()
+            .map_err(|kind| MalReaderError::new(kind, src, *offset))?;
+        tokens.push((*offset, token));
     }
 
     Ok(tokens)
 }
 
-fn apply_reader_macros(tokens: Vec<MalToken>) -> MalReaderResult<Vec<MalToken>> {
-    let mut transformed = vec![];
-    let len = tokens.len();
-    let mut i = 0;
-
-    loop {
-        if i >= len {
-            break;
-        }
-
-        let t = tokens.get(i).unwrap();
-        if let MalToken::Data(MalDataType::Symbol(s)) = t {
-            if s == "@" {
-                let next_t = tokens.get(i + 1).ok_or_else(|| {
-                    MalReaderError::LexingFailure(
-                        "@ reader macro improperly used, not followed by symbol".to_string(),
-                    )
-                })?;
-                transformed.extend_from_slice(&[
-                    MalToken::OpenParen,
-                    MalToken::Data(MalDataType::Symbol("deref".to_owned())),
-                    next_t.clone(),
-                    MalToken::CloseParen,
-                ]);
-                i += 2;
-                continue;
-            }
-        }
+fn reader(s: &str) -> MalReaderResult<Reader<'_>> {
+    let lexemes = lexer(s)?;
+    let tokens = tokenize(s, &lexemes)?;
+    Ok(Reader::new(s, tokens))
+}
 
-        transformed.push(t.clone());
-        i += 1;
+/// Reads exactly one form from `s`. Anything after it is an error.
+pub fn read_str(s: &str) -> MalReaderResult<MalDataType> {
+    let mut reader = reader(s)?;
+    reader.skip_discarded()?;
+    if reader.pos >= reader.tokens.len() {
+        return Err(MalReaderError::new(MalReaderErrorKind::NoForm, s, s.len()));
     }
 
-    Ok(transformed)
+    let form = reader.read_form()?;
+    reader.skip_discarded()?;
+    if let Ok((offset, token)) = reader.peek() {
+        return Err(reader.error(MalReaderErrorKind::TrailingInput(token.to_string()), offset));
+    }
+    Ok(form)
 }
 
-pub fn read_str(s: &str, _mal_env: &MalEnvironment) -> MalReaderResult<MalDataType> {
-    let lexemes = lexer(s)?;
-    // println!("lexemes: {:?}", lexemes);
-    let tokens = tokenize(&lexemes)?;
-    let tokens = apply_reader_macros(tokens)?;
-    // println!("tokens: {:?}", tokens);
-    let mut reader = Reader::new(tokens);
-
-    match reader.read_form()? {
-        MalToken::Data(d) => {
-            println!("d: {:?}", d);
-            Ok(d)
+/// Reads every top-level form in `s`, in order, e.g. a whole .mal file.
+pub fn read_all(s: &str) -> MalReaderResult<Vec<MalDataType>> {
+    let mut reader = reader(s)?;
+    let mut forms = vec![];
+    loop {
+        reader.skip_discarded()?;
+        if reader.pos >= reader.tokens.len() {
+            return Ok(forms);
         }
-        _ => Err(MalReaderError::UnterminatedList),
+        forms.push(reader.read_form()?);
     }
 }
 
@@ -257,23 +515,26 @@ mod tests {
     #[test]
     fn can_tokenize() -> MalReaderResult<()> {
         let lexemes = lexer("  (  + 2   ( *  3   4)   )   ")?;
+        let lexemes = lexemes.into_iter().map(|(_, l)| l).collect::<Vec<_>>();
         assert_eq!(lexemes, vec!["(", "+", "2", "(", "*", "3", "4", ")", ")"]);
         Ok(())
     }
 
     #[test]
     fn can_get_mal_tokens() -> MalReaderResult<()> {
-        let mal = MalEnvironment::new();
-        let mal_list = read_str("(+ 2 3 nil false)", &mal)?;
+        let mal_list = read_str("(+ 2 3 nil false)")?;
         assert_eq!(
             mal_list,
-            MalDataType::List(vec![
-                MalToken::Data(MalDataType::Symbol("+".to_owned())),
-                MalToken::Data(MalDataType::Int(2)),
-                MalToken::Data(MalDataType::Int(3)),
-                MalToken::Data(MalDataType::Nil),
-                MalToken::Data(MalDataType::Boolean(false)),
-            ])
+            MalDataType::List(
+                vec![
+                    MalDataType::Symbol("+".to_owned()),
+                    MalDataType::Int(2),
+                    MalDataType::Int(3),
+                    MalDataType::Nil,
+                    MalDataType::Boolean(false),
+                ],
+                None
+            )
         );
 
         Ok(())
@@ -281,9 +542,208 @@ mod tests {
 
     #[test]
     fn can_render_s() -> MalReaderResult<()> {
-        let mal = MalEnvironment::new();
-        let mal_list = read_str(" ( + 2   3 )  ", &mal)?;
+        let mal_list = read_str(" ( + 2   3 )  ")?;
         assert_eq!(mal_list.to_string(), "(+ 2 3)".to_owned());
         Ok(())
     }
+
+    #[test]
+    fn can_read_hash_map() -> MalReaderResult<()> {
+        let mal_map = read_str(r#"{"a" {:b 2}}"#)?;
+        assert_eq!(mal_map.to_string(), r#"{"a" {:b 2}}"#.to_owned());
+        Ok(())
+    }
+
+    #[test]
+    fn can_expand_reader_macros() -> MalReaderResult<()> {
+        let cases = [
+            ("'(1 2)", "(quote (1 2))"),
+            (
+                "`(1 ~a ~@[b c])",
+                "(quasiquote (1 (unquote a) (splice-unquote [b c])))",
+            ),
+            ("@(foo)", "(deref (foo))"),
+            (r#"^{"a" 1} [1 2]"#, r#"(with-meta [1 2] {"a" 1})"#),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(read_str(input)?.to_string(), expected.to_owned());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn can_read_nested_value_tree() -> MalReaderResult<()> {
+        let mal_list = read_str("(a [b (c)])")?;
+        assert_eq!(
+            mal_list,
+            MalDataType::List(
+                vec![
+                    MalDataType::Symbol("a".to_owned()),
+                    MalDataType::Vector(
+                        vec![
+                            MalDataType::Symbol("b".to_owned()),
+                            MalDataType::List(vec![MalDataType::Symbol("c".to_owned())], None),
+                        ],
+                        None
+                    ),
+                ],
+                None
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_mismatched_delimiters() {
+        let res = read_str("(1 2]");
+        assert!(matches!(
+            res,
+            Err(MalReaderError {
+                kind: MalReaderErrorKind::UnexpectedToken(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn can_read_numbers() -> MalReaderResult<()> {
+        let cases = [
+            ("-5", MalDataType::Int(-5)),
+            ("+7", MalDataType::Int(7)),
+            ("1.5", MalDataType::Float(1.5)),
+            ("1e10", MalDataType::Float(1e10)),
+            ("-0.25", MalDataType::Float(-0.25)),
+            ("-", MalDataType::Symbol("-".to_owned())),
+            ("-abc", MalDataType::Symbol("-abc".to_owned())),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(read_str(input)?, expected);
+        }
+        assert_eq!(read_str("2.0")?.to_string(), "2.0".to_owned());
+        for input in ["##Inf", "##-Inf", "##NaN"] {
+            assert_eq!(read_str(input)?.to_string(), input.to_owned());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_out_of_range_numbers() {
+        for input in [
+            "123456789012345678901234567890",
+            "-9223372036854775809",
+            "1e999",
+        ] {
+            let err = read_str(input).unwrap_err();
+            assert_eq!(
+                err.kind,
+                MalReaderErrorKind::NumberOutOfRange(input.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn can_decode_string_escapes() -> MalReaderResult<()> {
+        let data = read_str(r#""a\"b\\c\nd""#)?;
+        assert_eq!(data, MalDataType::String("a\"b\\c\nd".to_owned()));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unterminated_strings() {
+        for input in [r#""abc"#, r#"""#, r#""\""#] {
+            let err = read_str(input).unwrap_err();
+            assert!(matches!(err.kind, MalReaderErrorKind::IllegalString(_)));
+        }
+    }
+
+    #[test]
+    fn detects_incomplete_input() {
+        for input in ["(1 2", "[1 (2", "{:a", "(1 \"ab", "'", "(1 ~@"] {
+            assert!(read_str(input).unwrap_err().is_incomplete(), "{}", input);
+        }
+        for input in ["(1 2]", "  ", "(')"] {
+            assert!(!read_str(input).unwrap_err().is_incomplete(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn can_read_all_forms() -> MalReaderResult<()> {
+        let forms = read_all("(+ 1 2) (+ 3 4)\n:a")?;
+        let forms = forms.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert_eq!(forms, vec!["(+ 1 2)", "(+ 3 4)", ":a"]);
+        assert_eq!(read_all("  ")?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_trailing_input() {
+        let err = read_str("(+ 1 2) (+ 3 4)").unwrap_err();
+        assert_eq!(err.kind, MalReaderErrorKind::TrailingInput("(".to_owned()));
+        assert_eq!(err.pos.column, 9);
+    }
+
+    #[test]
+    fn drops_comments() -> MalReaderResult<()> {
+        let forms = read_all("(+ 1 2) ; sum\n;; whole line\n\"a;b\" ;")?;
+        let forms = forms.iter().map(|f| f.to_string()).collect::<Vec<_>>();
+        assert_eq!(forms, vec!["(+ 1 2)", r#""a;b""#]);
+        Ok(())
+    }
+
+    #[test]
+    fn can_discard_forms() -> MalReaderResult<()> {
+        let cases = [
+            ("(1 #_2 3)", "(1 3)"),
+            ("(1 #_(2 [3]) 4)", "(1 4)"),
+            ("[1 #_ #_ 2 3 4]", "[1 4]"),
+            ("#_a b", "b"),
+            ("(1 #_2)", "(1)"),
+            ("'#_a b", "(quote b)"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(read_str(input)?.to_string(), expected.to_owned());
+        }
+        assert_eq!(read_all("#_a")?, vec![]);
+        assert!(read_str("(1 #_").unwrap_err().is_incomplete());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_odd_hash_map_entries() {
+        let res = read_str(r#"{"a" 1 "b"}"#);
+        assert!(matches!(
+            res,
+            Err(MalReaderError {
+                kind: MalReaderErrorKind::OddHashMapEntries(3),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reports_position_of_unclosed_list() {
+        let src = "(def! a\n  (+ 1 (* 2 3))\n";
+        let err = read_str(src).unwrap_err();
+        assert_eq!(err.kind, MalReaderErrorKind::UnterminatedList);
+        assert_eq!(
+            err.pos,
+            MalSourcePos {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_error_with_caret() {
+        let src = "(a\n  b])";
+        let err = read_str(src).unwrap_err();
+        assert_eq!(err.pos.line, 2);
+        assert_eq!(err.pos.column, 4);
+        assert_eq!(
+            err.annotate(src),
+            "line 2, column 4: unbalanced, unexpected ']'\n  b])\n   ^"
+        );
+    }
 }
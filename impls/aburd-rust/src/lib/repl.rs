@@ -0,0 +1,199 @@
+use std::borrow::Cow;
+use std::env;
+
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper, Result};
+
+use crate::{environment::MalEnvironment, eval, read, MalDataType, MalResult};
+
+/// Functions defined in mal itself, evaluated by `new_env`.
+const PRELUDE: &[&str] = &[
+    "(def! not (fn* (a) (if a false true)))",
+    "(defmacro! cond (fn* (& xs) (if (> (count xs) 0) (list 'if (first xs) (if (> (count xs) 1) (nth xs 1) (throw \"odd number of forms to cond\")) (cons 'cond (rest (rest xs)))))))",
+];
+
+/// Where `run` keeps its history when built with `with-file-history`.
+pub const HISTORY: &str = "history.txt";
+
+/// Shown, dimmed, at the start of each continuation line until something
+/// is typed on it. rustyline has no continuation prompt, so it's a hint.
+const CONTINUATION: &str = "...> ";
+
+/// Makes rustyline keep reading while the input is an incomplete form,
+/// e.g. an open list or string, so a form spanning several lines can be
+/// edited as a whole and is stored in history as one entry.
+pub struct MalHelper;
+
+impl Validator for MalHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Completer for MalHelper {
+    type Candidate = String;
+}
+
+impl Hinter for MalHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        continuation_hint(line, pos).map(str::to_owned)
+    }
+}
+
+impl Highlighter for MalHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[2m{}\x1b[0m", hint))
+    }
+}
+
+impl Helper for MalHelper {}
+
+pub type MalEditor = Editor<MalHelper, DefaultHistory>;
+
+/// The continuation marker, if the cursor is at the start of an empty
+/// line that the validator added to an incomplete form.
+fn continuation_hint(line: &str, pos: usize) -> Option<&'static str> {
+    (pos == line.len() && line.ends_with('\n') && is_incomplete(line)).then_some(CONTINUATION)
+}
+
+/// True when more input could still complete `input`. Other reader errors
+/// are left for the caller to report.
+fn is_incomplete(input: &str) -> bool {
+    read::read_all(input).is_err_and(|e| e.is_incomplete())
+}
+
+/// An editor for the REPL prompt that reads whole forms. A dumb terminal,
+/// as the test harness uses, gets one line per read instead, so an
+/// unbalanced line is reported rather than waited on.
+pub fn editor() -> Result<MalEditor> {
+    let mut rl = MalEditor::new()?;
+    if env::var("TERM").map_or(true, |term| term != "dumb") {
+        rl.set_helper(Some(MalHelper));
+    }
+    Ok(rl)
+}
+
+/// A root environment with the prelude loaded, `*ARGV*` bound to `argv`
+/// and `*host-language*` set.
+pub fn new_env(argv: Vec<String>) -> MalEnvironment {
+    let mal_env = MalEnvironment::new();
+    for form in PRELUDE {
+        let ast = read::read_str(form).expect("prelude should read");
+        eval::eval(&ast, &mal_env).expect("prelude should evaluate");
+    }
+    let argv = argv.into_iter().map(MalDataType::String).collect();
+    mal_env.define("*ARGV*", MalDataType::List(argv, None));
+    mal_env.define("*host-language*", MalDataType::String("rust".to_owned()));
+    mal_env
+}
+
+/// Evaluates `(load-file path)` in `mal_env`.
+pub fn load_file(path: String, mal_env: &MalEnvironment) -> MalResult<MalDataType> {
+    let load = MalDataType::List(
+        vec![
+            MalDataType::Symbol("load-file".to_owned()),
+            MalDataType::String(path),
+        ],
+        None,
+    );
+    eval::eval(&load, mal_env)
+}
+
+/// Prints the `Mal [rust]` greeting the full interpreter starts with.
+pub fn banner(mal_env: &MalEnvironment) {
+    let banner = read::read_str(r#"(println (str "Mal [" *host-language* "]"))"#)
+        .expect("banner should read");
+    eval::eval(&banner, mal_env).expect("banner should evaluate");
+}
+
+/// Reads the forms in `line` and passes each to `f`, or reports where
+/// reading failed.
+pub fn each_form(line: &str, f: impl FnMut(MalDataType)) {
+    match read::read_all(line) {
+        Ok(forms) => forms.into_iter().for_each(f),
+        Err(e) => eprintln!("{}", e.annotate(line)),
+    }
+}
+
+/// Evaluates the forms in `line` in `mal_env`, printing each result.
+pub fn rep(line: &str, mal_env: &MalEnvironment) {
+    each_form(line, |form| match eval::eval(&form, mal_env) {
+        Ok(value) => println!("{}", value),
+        Err(e) => eprintln!("Error: {}", e),
+    })
+}
+
+/// Prompts with `user> ` and passes each input to `rep` until CTRL-C or
+/// CTRL-D. Built with `with-file-history`, the history is loaded from and
+/// saved to `history`.
+#[cfg_attr(not(feature = "with-file-history"), allow(unused_variables))]
+pub fn run<H: Helper>(
+    rl: &mut Editor<H, DefaultHistory>,
+    history: &str,
+    mut rep: impl FnMut(&str),
+) -> Result<()> {
+    #[cfg(feature = "with-file-history")]
+    if rl.load_history(history).is_err() {
+        println!("No previous history.");
+    }
+
+    loop {
+        let readline = rl.readline("user> ");
+        match readline {
+            Ok(line) => {
+                rl.add_history_entry(line.as_str())?;
+                rep(&line);
+            }
+            Err(ReadlineError::Interrupted) => {
+                println!("CTRL-C");
+                break;
+            }
+            Err(ReadlineError::Eof) => {
+                println!("CTRL-D");
+                break;
+            }
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
+            }
+        }
+    }
+
+    #[cfg(feature = "with-file-history")]
+    rl.save_history(history)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_incomplete_forms() {
+        for input in ["(+ 1", "[1 (2", "{\"a\"", "\"abc", "'", "(+ 1 2) (foo"] {
+            assert!(is_incomplete(input), "{}", input);
+        }
+        for input in ["", "(+ 1\n2)", "\"a\nb\"", "1 2", ")", "(+ 1 2))"] {
+            assert!(!is_incomplete(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn marks_empty_continuation_lines() {
+        assert_eq!(continuation_hint("(+ 1\n", 5), Some(CONTINUATION));
+        assert_eq!(continuation_hint("(+ 1\n2", 6), None);
+        assert_eq!(continuation_hint("(+ 1\n", 2), None);
+        assert_eq!(continuation_hint("(+ 1", 4), None);
+    }
+}
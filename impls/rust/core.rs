@@ -1,27 +1,27 @@
+use std::cmp::Ordering;
 use std::fs::File;
 use std::io::Read;
 use std::rc::Rc;
 use std::sync::Mutex;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, Signed, ToPrimitive};
+
 extern crate rustyline;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
 use crate::printer::pr_seq;
 use crate::reader::read_str;
-use crate::types::MalErr::ErrMalVal;
-use crate::types::MalVal::{Atom, Bool, Func, Hash, Int, List, MalFunc, Nil, Str, Sym, Vector};
-use crate::types::{MalArgs, MalRet, MalVal, _assoc, _dissoc, atom, error, func, hash_map};
-
-macro_rules! fn_t_int_int {
-    ($ret:ident, $fn:expr) => {{
-        |a: MalArgs| match (a[0].clone(), a[1].clone()) {
-            (Int(a0), Int(a1)) => Ok($ret($fn(a0, a1))),
-            _ => error("expecting (int,int) args"),
-        }
-    }};
-}
+use crate::types::MalErr::{ErrMalVal, ErrString};
+use crate::types::MalVal::{
+    Atom, Big, Bool, Float, Func, Hash, Int, Keyword, List, MalFunc, Nil, Ratio, Str, Sym, Vector,
+};
+use crate::types::{
+    MalArgs, MalErr, MalKey, MalRet, MalVal, _assoc, _dissoc, atom, bigint, error, func, hash_map, ratio,
+};
 
 macro_rules! fn_is_type {
   ($($ps:pat),*) => {{
@@ -79,6 +79,194 @@ fn readline(a: MalArgs) -> MalRet {
     }
 }
 
+fn to_ratio(name: &str, x: &MalVal) -> Result<BigRational, MalErr> {
+    match x {
+        Int(i) => Ok(BigRational::from_integer(BigInt::from(*i))),
+        Big(b) => Ok(BigRational::from_integer(b.clone())),
+        Ratio(r) => Ok(r.clone()),
+        _ => Err(ErrString(format!("{}: expecting number args", name))),
+    }
+}
+
+fn to_f64(name: &str, x: &MalVal) -> Result<f64, MalErr> {
+    match x {
+        Int(i) => Ok(*i as f64),
+        Big(b) => Ok(b.to_f64().unwrap_or(f64::NAN)),
+        Ratio(r) => Ok(ratio_to_f64(r)),
+        Float(f) => Ok(*f),
+        _ => Err(ErrString(format!("{}: expecting number args", name))),
+    }
+}
+
+// Shift numerator and denominator right by the same number of bits so that
+// neither overflows an f64, which would otherwise give inf / inf = NaN.
+fn ratio_to_f64(r: &BigRational) -> f64 {
+    let (n, d) = (r.numer(), r.denom());
+    let shift = n.bits().max(d.bits()).saturating_sub(1000);
+    let q = match ((n.abs() >> shift).to_f64(), (d >> shift).to_f64()) {
+        (Some(n), Some(d)) => n / d,
+        _ => f64::NAN,
+    };
+    if n.is_negative() {
+        -q
+    } else {
+        q
+    }
+}
+
+fn is_float(x: &MalVal) -> bool {
+    match x {
+        Float(_) => true,
+        _ => false,
+    }
+}
+
+// Two Ints use int_fn, which returns None on overflow. If either side is a
+// Float, both are promoted and float_fn is used. Everything else, including
+// Int overflow, is done exactly with exact_fn.
+fn num_op(
+    name: &str,
+    acc: MalVal,
+    x: &MalVal,
+    int_fn: fn(i64, i64) -> Option<i64>,
+    exact_fn: fn(BigRational, BigRational) -> BigRational,
+    float_fn: fn(f64, f64) -> f64,
+) -> MalRet {
+    if let (Int(a0), Int(a1)) = (&acc, x) {
+        if let Some(i) = int_fn(*a0, *a1) {
+            return Ok(Int(i));
+        }
+    }
+    if is_float(&acc) || is_float(x) {
+        return Ok(Float(float_fn(to_f64(name, &acc)?, to_f64(name, x)?)));
+    }
+    Ok(ratio(exact_fn(to_ratio(name, &acc)?, to_ratio(name, x)?)))
+}
+
+fn fold_num(
+    name: &str,
+    init: MalVal,
+    args: &[MalVal],
+    int_fn: fn(i64, i64) -> Option<i64>,
+    exact_fn: fn(BigRational, BigRational) -> BigRational,
+    float_fn: fn(f64, f64) -> f64,
+) -> MalRet {
+    args.iter().try_fold(init, |acc, x| {
+        num_op(name, acc, x, int_fn, exact_fn, float_fn)
+    })
+}
+
+fn add(a: MalArgs) -> MalRet {
+    fold_num(
+        "+",
+        Int(0),
+        &a,
+        i64::checked_add,
+        |x, y| x + y,
+        |x, y| x + y,
+    )
+}
+
+fn mul(a: MalArgs) -> MalRet {
+    fold_num(
+        "*",
+        Int(1),
+        &a,
+        i64::checked_mul,
+        |x, y| x * y,
+        |x, y| x * y,
+    )
+}
+
+// (- x) is (- 0 x), the same as in Clojure
+fn sub(a: MalArgs) -> MalRet {
+    let (init, rest) = match a.len() {
+        0 => return error("-: expecting at least 1 arg"),
+        1 => (Int(0), &a[..]),
+        _ => (a[0].clone(), &a[1..]),
+    };
+    fold_num(
+        "-",
+        init,
+        rest,
+        i64::checked_sub,
+        |x, y| x - y,
+        |x, y| x - y,
+    )
+}
+
+// Only Ints that divide evenly stay Ints, otherwise (/ 1 3) is the Ratio
+// 1/3. (/ x) is (/ 1 x). Dividing an exact number by zero is an error,
+// while Floats follow IEEE and give ##Inf or ##NaN.
+fn div(a: MalArgs) -> MalRet {
+    let (init, rest) = match a.len() {
+        0 => return error("/: expecting at least 1 arg"),
+        1 => (Int(1), &a[..]),
+        _ => (a[0].clone(), &a[1..]),
+    };
+    rest.iter().try_fold(init, |acc, x| match (&acc, x) {
+        (Int(_), Int(0)) | (Big(_), Int(0)) | (Ratio(_), Int(0)) => error("/: divide by zero"),
+        (Int(a0), Int(a1)) if a0.checked_rem(*a1) != Some(0) => Ok(ratio(BigRational::new(
+            BigInt::from(*a0),
+            BigInt::from(*a1),
+        ))),
+        _ => num_op("/", acc, x, i64::checked_div, |x, y| x / y, |x, y| x / y),
+    })
+}
+
+fn num_cmp(name: &str, a: &MalVal, b: &MalVal) -> Result<Option<Ordering>, MalErr> {
+    if let (Int(a0), Int(a1)) = (a, b) {
+        return Ok(Some(a0.cmp(a1)));
+    }
+    if is_float(a) || is_float(b) {
+        return Ok(to_f64(name, a)?.partial_cmp(&to_f64(name, b)?));
+    }
+    Ok(Some(to_ratio(name, a)?.cmp(&to_ratio(name, b)?)))
+}
+
+// Chained comparison, so (< 1 2 3) checks every adjacent pair
+fn compare(name: &str, a: &MalArgs, pred: fn(Ordering) -> bool) -> MalRet {
+    if a.is_empty() {
+        return error(&format!("{}: expecting at least 1 arg", name));
+    }
+    for pair in a.windows(2) {
+        match num_cmp(name, &pair[0], &pair[1])? {
+            Some(o) if pred(o) => {}
+            _ => return Ok(Bool(false)),
+        }
+    }
+    Ok(Bool(true))
+}
+
+fn equal(a: MalArgs) -> MalRet {
+    if a.is_empty() {
+        return error("=: expecting at least 1 arg");
+    }
+    Ok(Bool(a.windows(2).all(|pair| pair[0] == pair[1])))
+}
+
+fn int(a: MalArgs) -> MalRet {
+    if a.len() != 1 {
+        return error("int: expecting 1 arg");
+    }
+    match a[0] {
+        Int(_) | Big(_) => Ok(a[0].clone()),
+        Ratio(ref r) => Ok(bigint(r.trunc().to_integer())),
+        Float(f) => match BigInt::from_f64(f.trunc()) {
+            Some(b) => Ok(bigint(b)),
+            None => error("int: cannot convert non-finite float"),
+        },
+        _ => error("int: expecting number"),
+    }
+}
+
+fn double(a: MalArgs) -> MalRet {
+    if a.len() != 1 {
+        return error("double: expecting 1 arg");
+    }
+    Ok(Float(to_f64("double", &a[0])?))
+}
+
 fn slurp(f: String) -> MalRet {
     let mut s = String::new();
     match File::open(f).and_then(|mut f| f.read_to_string(&mut s)) {
@@ -98,9 +286,9 @@ fn time_ms(_a: MalArgs) -> MalRet {
 }
 
 fn get(a: MalArgs) -> MalRet {
-    match (a[0].clone(), a[1].clone()) {
-        (Nil, _) => Ok(Nil),
-        (Hash(ref hm, _), Str(ref s)) => match hm.get(s) {
+    match a[0] {
+        Nil => Ok(Nil),
+        Hash(ref hm, _) => match hm.get(&a[1].to_key()) {
             Some(mv) => Ok(mv.clone()),
             None => Ok(Nil),
         },
@@ -123,15 +311,15 @@ fn dissoc(a: MalArgs) -> MalRet {
 }
 
 fn contains_q(a: MalArgs) -> MalRet {
-    match (a[0].clone(), a[1].clone()) {
-        (Hash(ref hm, _), Str(ref s)) => Ok(Bool(hm.contains_key(s))),
+    match a[0] {
+        Hash(ref hm, _) => Ok(Bool(hm.contains_key(&a[1].to_key()))),
         _ => error("illegal get args"),
     }
 }
 
 fn keys(a: MalArgs) -> MalRet {
     match a[0] {
-        Hash(ref hm, _) => Ok(list!(hm.keys().map(|k| { Str(k.to_string()) }).collect())),
+        Hash(ref hm, _) => Ok(list!(hm.keys().map(MalKey::to_val).collect())),
         _ => error("keys requires Hash Map"),
     }
 }
@@ -252,9 +440,7 @@ fn seq(a: MalArgs) -> MalRet {
         List(ref v, _) | Vector(ref v, _) if v.len() == 0 => Ok(Nil),
         List(ref v, _) | Vector(ref v, _) => Ok(list!(v.to_vec())),
         Str(ref s) if s.len() == 0 => Ok(Nil),
-        Str(ref s) if !a[0].keyword_q() => {
-            Ok(list!(s.chars().map(|c| { Str(c.to_string()) }).collect()))
-        }
+        Str(ref s) => Ok(list!(s.chars().map(|c| { Str(c.to_string()) }).collect())),
         Nil => Ok(Nil),
         _ => error("seq: called with non-seq"),
     }
@@ -262,23 +448,23 @@ fn seq(a: MalArgs) -> MalRet {
 
 pub fn ns() -> Vec<(&'static str, MalVal)> {
     vec![
-        ("=", func(|a| Ok(Bool(a[0] == a[1])))),
+        ("=", func(equal)),
         ("throw", func(|a| Err(ErrMalVal(a[0].clone())))),
         ("nil?", func(fn_is_type!(Nil))),
         ("true?", func(fn_is_type!(Bool(true)))),
         ("false?", func(fn_is_type!(Bool(false)))),
         ("symbol", func(symbol)),
         ("symbol?", func(fn_is_type!(Sym(_)))),
-        (
-            "string?",
-            func(fn_is_type!(Str(ref s) if !s.starts_with("\u{29e}"))),
-        ),
+        ("string?", func(fn_is_type!(Str(_)))),
         ("keyword", func(|a| a[0].keyword())),
+        ("keyword?", func(fn_is_type!(Keyword(_)))),
         (
-            "keyword?",
-            func(fn_is_type!(Str(ref s) if s.starts_with("\u{29e}"))),
+            "number?",
+            func(fn_is_type!(Int(_), Big(_), Ratio(_), Float(_))),
         ),
-        ("number?", func(fn_is_type!(Int(_)))),
+        ("float?", func(fn_is_type!(Float(_)))),
+        ("int", func(int)),
+        ("double", func(double)),
         (
             "fn?",
             func(fn_is_type!(MalFunc{is_macro,..} if !is_macro,Func(_,_))),
@@ -306,14 +492,17 @@ pub fn ns() -> Vec<(&'static str, MalVal)> {
         ("read-string", func(fn_str!(|s| { read_str(s) }))),
         ("readline", func(readline)),
         ("slurp", func(fn_str!(|f| { slurp(f) }))),
-        ("<", func(fn_t_int_int!(Bool, |i, j| { i < j }))),
-        ("<=", func(fn_t_int_int!(Bool, |i, j| { i <= j }))),
-        (">", func(fn_t_int_int!(Bool, |i, j| { i > j }))),
-        (">=", func(fn_t_int_int!(Bool, |i, j| { i >= j }))),
-        ("+", func(fn_t_int_int!(Int, |i, j| { i + j }))),
-        ("-", func(fn_t_int_int!(Int, |i, j| { i - j }))),
-        ("*", func(fn_t_int_int!(Int, |i, j| { i * j }))),
-        ("/", func(fn_t_int_int!(Int, |i, j| { i / j }))),
+        ("<", func(|a| compare("<", &a, |o| o == Ordering::Less))),
+        (
+            "<=",
+            func(|a| compare("<=", &a, |o| o != Ordering::Greater)),
+        ),
+        (">", func(|a| compare(">", &a, |o| o == Ordering::Greater))),
+        (">=", func(|a| compare(">=", &a, |o| o != Ordering::Less))),
+        ("+", func(add)),
+        ("-", func(sub)),
+        ("*", func(mul)),
+        ("/", func(div)),
         ("time-ms", func(time_ms)),
         ("sequential?", func(fn_is_type!(List(_, _), Vector(_, _)))),
         ("list", func(|a| Ok(list!(a)))),
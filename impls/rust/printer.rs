@@ -1,5 +1,7 @@
 use crate::types::MalVal;
-use crate::types::MalVal::{Atom, Bool, Func, Hash, Int, List, MalFunc, Nil, Str, Sym, Vector};
+use crate::types::MalVal::{
+    Atom, Big, Bool, Float, Func, Hash, Int, Keyword, List, MalFunc, Nil, Ratio, Str, Sym, Vector,
+};
 
 fn escape_str(s: &str) -> String {
     s.chars()
@@ -20,23 +22,28 @@ impl MalVal {
             Bool(true) => String::from("true"),
             Bool(false) => String::from("false"),
             Int(i) => format!("{}", i),
-            //Float(f)    => format!("{}", f),
+            Big(b) => format!("{}N", b),
+            Ratio(r) => format!("{}/{}", r.numer(), r.denom()),
+            // {:?} always keeps a '.' or exponent, so floats read back as floats
+            Float(f) if f.is_nan() => String::from("##NaN"),
+            Float(f) if f.is_infinite() && *f > 0.0 => String::from("##Inf"),
+            Float(f) if f.is_infinite() => String::from("##-Inf"),
+            Float(f) => format!("{:?}", f),
             Str(s) => {
-                if s.starts_with("\u{29e}") {
-                    format!(":{}", &s[2..])
-                } else if print_readably {
+                if print_readably {
                     format!("\"{}\"", escape_str(s))
                 } else {
                     s.clone()
                 }
             }
+            Keyword(k) => format!(":{}", k),
             Sym(s) => s.clone(),
             List(l, _) => pr_seq(&**l, print_readably, "(", ")", " "),
             Vector(l, _) => pr_seq(&**l, print_readably, "[", "]", " "),
             Hash(hm, _) => {
                 let l: Vec<MalVal> = hm
                     .iter()
-                    .flat_map(|(k, v)| vec![Str(k.to_string()), v.clone()])
+                    .flat_map(|(k, v)| vec![k.to_val(), v.clone()])
                     .collect();
                 pr_seq(&l, print_readably, "{", "}", " ")
             }
@@ -1,9 +1,12 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::Zero;
 use regex::{Captures, Regex};
 use std::rc::Rc;
 
 use crate::types::MalErr::ErrString;
-use crate::types::MalVal::{Bool, Int, List, Nil, Str, Sym, Vector};
-use crate::types::{error, hash_map, MalErr, MalRet, MalVal};
+use crate::types::MalVal::{Bool, Float, Int, List, Nil, Str, Sym, Vector};
+use crate::types::{bigint, error, hash_map, keyword, ratio, MalErr, MalRet, MalVal};
 
 #[derive(Debug, Clone)]
 struct Reader {
@@ -60,6 +63,10 @@ fn unescape_str(s: &str) -> String {
 fn read_atom(rdr: &mut Reader) -> MalRet {
     lazy_static! {
         static ref INT_RE: Regex = Regex::new(r"^-?[0-9]+$").unwrap();
+        static ref BIGINT_RE: Regex = Regex::new(r"^-?[0-9]+N$").unwrap();
+        static ref RATIO_RE: Regex = Regex::new(r"^(-?[0-9]+)/([0-9]+)$").unwrap();
+        static ref FLOAT_RE: Regex =
+            Regex::new(r"^-?[0-9]+(\.[0-9]+([eE][-+]?[0-9]+)?|[eE][-+]?[0-9]+)$").unwrap();
         static ref STR_RE: Regex = Regex::new(r#""(?:\\.|[^\\"])*""#).unwrap();
     }
     let token = rdr.next()?;
@@ -67,15 +74,34 @@ fn read_atom(rdr: &mut Reader) -> MalRet {
         "nil" => Ok(Nil),
         "false" => Ok(Bool(false)),
         "true" => Ok(Bool(true)),
+        "##Inf" => Ok(Float(f64::INFINITY)),
+        "##-Inf" => Ok(Float(f64::NEG_INFINITY)),
+        "##NaN" => Ok(Float(f64::NAN)),
         _ => {
             if INT_RE.is_match(&token) {
-                Ok(Int(token.parse().unwrap()))
+                match token.parse() {
+                    Ok(i) => Ok(Int(i)),
+                    Err(_) => Ok(bigint(token.parse().unwrap())),
+                }
+            } else if BIGINT_RE.is_match(&token) {
+                // Normalised like any exact number, so 123N reads as the
+                // Int 123 and prints back as 123. Only values outside the
+                // i64 range stay Big and print with the N suffix.
+                Ok(bigint(token[..token.len() - 1].parse().unwrap()))
+            } else if let Some(caps) = RATIO_RE.captures(&token) {
+                let d: BigInt = caps[2].parse().unwrap();
+                if d.is_zero() {
+                    return error("divide by zero in ratio");
+                }
+                Ok(ratio(BigRational::new(caps[1].parse().unwrap(), d)))
+            } else if FLOAT_RE.is_match(&token) {
+                Ok(Float(token.parse().unwrap()))
             } else if STR_RE.is_match(&token) {
                 Ok(Str(unescape_str(&token[1..token.len() - 1])))
             } else if token.starts_with("\"") {
                 error("expected '\"', got EOF")
             } else if token.starts_with(":") {
-                Ok(Str(format!("\u{29e}{}", &token[1..])))
+                Ok(keyword(&token[1..]))
             } else {
                 Ok(Sym(token.to_string()))
             }
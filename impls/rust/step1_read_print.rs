@@ -2,6 +2,9 @@
 extern crate lazy_static;
 extern crate fnv;
 extern crate itertools;
+extern crate num_bigint;
+extern crate num_rational;
+extern crate num_traits;
 extern crate regex;
 
 extern crate rustyline;
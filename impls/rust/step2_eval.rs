@@ -6,6 +6,9 @@ use fnv::FnvHashMap;
 extern crate lazy_static;
 extern crate fnv;
 extern crate itertools;
+extern crate num_bigint;
+extern crate num_rational;
+extern crate num_traits;
 extern crate regex;
 
 extern crate rustyline;
@@ -17,7 +20,7 @@ use rustyline::Editor;
 mod types;
 use crate::types::MalErr::ErrString;
 use crate::types::MalVal::{Hash, Int, List, Nil, Sym, Vector};
-use crate::types::{error, format_error, func, MalArgs, MalErr, MalRet, MalVal};
+use crate::types::{error, format_error, func, hash_map, MalArgs, MalErr, MalRet, MalVal};
 mod printer;
 mod reader;
 // TODO: figure out a way to avoid including env
@@ -53,11 +56,14 @@ fn eval_ast(ast: &MalVal, env: &Env) -> MalRet {
             Ok(vector!(lst))
         }
         Hash(hm, _) => {
-            let mut new_hm: FnvHashMap<String, MalVal> = FnvHashMap::default();
+            // The keys of a map literal are evaluated like its values, so
+            // {a 1} needs a to be bound
+            let mut kvs: MalArgs = vec![];
             for (k, v) in hm.iter() {
-                new_hm.insert(k.to_string(), eval(v.clone(), env.clone())?);
+                kvs.push(eval(k.to_val(), env.clone())?);
+                kvs.push(eval(v.clone(), env.clone())?);
             }
-            Ok(Hash(Rc::new(new_hm), Rc::new(Nil)))
+            hash_map(kvs)
         }
         _ => Ok(ast.clone()),
     }
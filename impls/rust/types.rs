@@ -1,24 +1,37 @@
 use std::cell::RefCell;
+use std::hash::Hash as _;
+use std::hash::Hasher;
 use std::rc::Rc;
 //use std::collections::HashMap;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet, FnvHasher};
 use itertools::Itertools;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
 
 use crate::env::{env_bind, Env};
 use crate::types::MalErr::{ErrMalVal, ErrString};
-use crate::types::MalVal::{Atom, Bool, Func, Hash, Int, List, MalFunc, Nil, Str, Sym, Vector};
+use crate::types::MalVal::{
+    Atom, Big, Bool, Float, Func, Hash, Int, Keyword, List, MalFunc, Nil, Ratio, Str, Sym, Vector,
+};
 
 #[derive(Debug, Clone)]
 pub enum MalVal {
     Nil,
     Bool(bool),
     Int(i64),
-    //Float(f64),
+    // Only for integers outside the i64 range, see bigint()
+    Big(BigInt),
+    // Never a whole number, see ratio()
+    Ratio(BigRational),
+    Float(f64),
     Str(String),
+    // Interned, see keyword()
+    Keyword(Rc<str>),
     Sym(String),
     List(Rc<Vec<MalVal>>, Rc<MalVal>),
     Vector(Rc<Vec<MalVal>>, Rc<MalVal>),
-    Hash(Rc<FnvHashMap<String, MalVal>>, Rc<MalVal>),
+    Hash(Rc<FnvHashMap<MalKey, MalVal>>, Rc<MalVal>),
     Func(fn(MalArgs) -> MalRet, Rc<MalVal>),
     MalFunc {
         eval: fn(ast: MalVal, env: Env) -> MalRet,
@@ -31,6 +44,11 @@ pub enum MalVal {
     Atom(Rc<RefCell<MalVal>>),
 }
 
+// Any value can be a hash-map key. Keys compare like =, except that NaN
+// equals itself, so every key can find its own entry again.
+#[derive(Debug, Clone)]
+pub struct MalKey(MalVal);
+
 #[derive(Debug)]
 pub enum MalErr {
     ErrString(String),
@@ -75,15 +93,52 @@ pub fn format_error(e: MalErr) -> String {
     }
 }
 
+// Exact numbers are kept in the smallest type that holds them, so each
+// value has one representation: Int if it fits in an i64, else Big
+pub fn bigint(b: BigInt) -> MalVal {
+    match b.to_i64() {
+        Some(i) => Int(i),
+        None => Big(b),
+    }
+}
+
+pub fn ratio(r: BigRational) -> MalVal {
+    if r.is_integer() {
+        bigint(r.to_integer())
+    } else {
+        Ratio(r)
+    }
+}
+
+// Keywords with the same name share one Rc<str>
+pub fn keyword(name: &str) -> MalVal {
+    thread_local! {
+        static KEYWORDS: RefCell<FnvHashSet<Rc<str>>> = RefCell::new(FnvHashSet::default());
+    }
+    KEYWORDS.with(|kws| {
+        let mut kws = kws.borrow_mut();
+        if let Some(k) = kws.get(name) {
+            return Keyword(k.clone());
+        }
+        let k: Rc<str> = Rc::from(name);
+        kws.insert(k.clone());
+        Keyword(k)
+    })
+}
+
 pub fn atom(mv: &MalVal) -> MalVal {
     Atom(Rc::new(RefCell::new(mv.clone())))
 }
 
 impl MalVal {
+    pub fn to_key(&self) -> MalKey {
+        MalKey(self.clone())
+    }
+
     pub fn keyword(&self) -> MalRet {
         match self {
-            Str(s) if s.starts_with("\u{29e}") => Ok(Str(s.to_string())),
-            Str(s) => Ok(Str(format!("\u{29e}{}", s))),
+            Keyword(_) => Ok(self.clone()),
+            Str(s) => Ok(keyword(s)),
             _ => error("invalid type for keyword"),
         }
     }
@@ -123,13 +178,6 @@ impl MalVal {
         }
     }
 
-    pub fn keyword_q(&self) -> bool {
-        match self {
-            Str(s) if s.starts_with("\u{29e}") => true,
-            _ => false,
-        }
-    }
-
     pub fn deref(&self) -> MalRet {
         match self {
             Atom(a) => Ok(a.borrow().clone()),
@@ -190,51 +238,147 @@ impl PartialEq for MalVal {
             (Nil, Nil) => true,
             (Bool(ref a), Bool(ref b)) => a == b,
             (Int(ref a), Int(ref b)) => a == b,
+            (Big(ref a), Big(ref b)) => a == b,
+            (Ratio(ref a), Ratio(ref b)) => a == b,
+            (Float(ref a), Float(ref b)) => a == b,
             (Str(ref a), Str(ref b)) => a == b,
+            (Keyword(ref a), Keyword(ref b)) => a == b,
             (Sym(ref a), Sym(ref b)) => a == b,
             (List(ref a, _), List(ref b, _))
             | (Vector(ref a, _), Vector(ref b, _))
             | (List(ref a, _), Vector(ref b, _))
             | (Vector(ref a, _), List(ref b, _)) => a == b,
             (Hash(ref a, _), Hash(ref b, _)) => a == b,
-            (MalFunc { .. }, MalFunc { .. }) => false,
+            (Func(_, ref m1), Func(_, ref m2)) => Rc::ptr_eq(m1, m2),
+            (
+                MalFunc {
+                    ast: ref a1,
+                    env: ref e1,
+                    meta: ref m1,
+                    ..
+                },
+                MalFunc {
+                    ast: ref a2,
+                    env: ref e2,
+                    meta: ref m2,
+                    ..
+                },
+            ) => Rc::ptr_eq(a1, a2) && Rc::ptr_eq(e1, e2) && Rc::ptr_eq(m1, m2),
+            (Atom(ref a), Atom(ref b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
 }
 
+impl MalKey {
+    pub fn to_val(&self) -> MalVal {
+        self.0.clone()
+    }
+}
+
+fn key_eq(a: &MalVal, b: &MalVal) -> bool {
+    match (a, b) {
+        (Float(ref a), Float(ref b)) => a == b || (a.is_nan() && b.is_nan()),
+        (List(ref a, _), List(ref b, _))
+        | (Vector(ref a, _), Vector(ref b, _))
+        | (List(ref a, _), Vector(ref b, _))
+        | (Vector(ref a, _), List(ref b, _)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| key_eq(x, y))
+        }
+        (Hash(ref a, _), Hash(ref b, _)) => {
+            a.len() == b.len()
+                && a.iter().all(|(k, v)| match b.get(k) {
+                    Some(bv) => key_eq(v, bv),
+                    None => false,
+                })
+        }
+        _ => a == b,
+    }
+}
+
+impl PartialEq for MalKey {
+    fn eq(&self, other: &MalKey) -> bool {
+        key_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for MalKey {}
+
+// Must agree with key_eq: lists hash like vectors, 0.0 like -0.0, and a
+// map's hash doesn't depend on its iteration order
+fn key_hash<H: Hasher>(mv: &MalVal, state: &mut H) {
+    match mv {
+        Nil => 0.hash(state),
+        Bool(b) => (1, b).hash(state),
+        Int(i) => (2, i).hash(state),
+        Big(b) => (3, b).hash(state),
+        Ratio(r) => (4, r).hash(state),
+        Float(f) => {
+            let bits = if f.is_nan() {
+                f64::NAN.to_bits()
+            } else if *f == 0.0 {
+                0
+            } else {
+                f.to_bits()
+            };
+            (5, bits).hash(state)
+        }
+        Str(s) => (6, s).hash(state),
+        Keyword(k) => (7, k).hash(state),
+        Sym(s) => (8, s).hash(state),
+        List(l, _) | Vector(l, _) => {
+            (9, l.len()).hash(state);
+            for x in l.iter() {
+                key_hash(x, state);
+            }
+        }
+        Hash(hm, _) => {
+            let mut sum: u64 = 0;
+            for (k, v) in hm.iter() {
+                let mut h = FnvHasher::default();
+                k.hash(&mut h);
+                key_hash(v, &mut h);
+                sum = sum.wrapping_add(h.finish());
+            }
+            (10, hm.len(), sum).hash(state)
+        }
+        Func(_, m) => (11, &**m as *const MalVal).hash(state),
+        MalFunc { ast, .. } => (12, &**ast as *const MalVal).hash(state),
+        Atom(a) => (13, &**a as *const RefCell<MalVal>).hash(state),
+    }
+}
+
+impl std::hash::Hash for MalKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        key_hash(&self.0, state)
+    }
+}
+
 pub fn func(f: fn(MalArgs) -> MalRet) -> MalVal {
     Func(f, Rc::new(Nil))
 }
 
-pub fn _assoc(mut hm: FnvHashMap<String, MalVal>, kvs: MalArgs) -> MalRet {
+// MalKey compares and hashes atoms and functions by address, never by
+// their contents, so mutating through a key can't change where it belongs
+#[allow(clippy::mutable_key_type)]
+pub fn _assoc(mut hm: FnvHashMap<MalKey, MalVal>, kvs: MalArgs) -> MalRet {
     if kvs.len() % 2 != 0 {
         return error("odd number of elements");
     }
     for (k, v) in kvs.iter().tuples() {
-        match k {
-            Str(s) => {
-                hm.insert(s.to_string(), v.clone());
-            }
-            _ => return error("key is not string"),
-        }
+        hm.insert(k.to_key(), v.clone());
     }
     Ok(Hash(Rc::new(hm), Rc::new(Nil)))
 }
 
-pub fn _dissoc(mut hm: FnvHashMap<String, MalVal>, ks: MalArgs) -> MalRet {
+#[allow(clippy::mutable_key_type)]
+pub fn _dissoc(mut hm: FnvHashMap<MalKey, MalVal>, ks: MalArgs) -> MalRet {
     for k in ks.iter() {
-        match k {
-            Str(ref s) => {
-                hm.remove(s);
-            }
-            _ => return error("key is not string"),
-        }
+        hm.remove(&k.to_key());
     }
     Ok(Hash(Rc::new(hm), Rc::new(Nil)))
 }
 
 pub fn hash_map(kvs: MalArgs) -> MalRet {
-    let hm: FnvHashMap<String, MalVal> = FnvHashMap::default();
-    _assoc(hm, kvs)
+    _assoc(FnvHashMap::default(), kvs)
 }